@@ -0,0 +1,306 @@
+use regex::Regex;
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct UrlCleaning {
+    pub enabled: bool,
+    pub strip_params: Vec<String>,
+    pub redirects: Vec<Redirect>,
+}
+impl Default for UrlCleaning {
+    fn default() -> Self {
+        UrlCleaning {
+            enabled: true,
+            strip_params: [
+                "^utm_",
+                "^(fb|g|y|ms|dc)clid$",
+                "^mc_(cid|eid)$",
+                "^_hs(enc|mi)$",
+                "^mkt_tok$",
+                "^igshid$",
+                "^ref_src$",
+                "^wt_mc$",
+                "^__twitter_impression$",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+            redirects: vec![
+                Redirect::new(r"^(www\.)?google\.[a-z.]+$", "/url", "q"),
+                Redirect::new(r"^l\.facebook\.com$", "/l.php", "u"),
+                Redirect::new(r"^out\.reddit\.com$", "", "url"),
+                Redirect::new(r"^t\.umblr\.com$", "/redirect", "z"),
+                Redirect::new(r"^(www\.)?youtube\.com$", "/redirect", "q"),
+                Redirect::new(r"^news\.google\.com$", "", "url"),
+            ],
+        }
+    }
+}
+
+// a redirect wrapper whose target is carried in a query parameter, so
+// that it can be unwrapped without a network round-trip
+#[derive(Deserialize, Debug, Clone)]
+pub struct Redirect {
+    pub host: String,
+    #[serde(default)]
+    pub path: String,
+    pub param: String,
+}
+impl Redirect {
+    fn new(host: &str, path: &str, param: &str) -> Self {
+        Redirect {
+            host: host.to_string(),
+            path: path.to_string(),
+            param: param.to_string(),
+        }
+    }
+}
+
+pub struct UrlCleaner {
+    enabled: bool,
+    params: Vec<Regex>,
+    redirects: Vec<(Regex, String, String)>,
+}
+impl UrlCleaner {
+    pub fn new(conf: &UrlCleaning) -> Self {
+        UrlCleaner {
+            enabled: conf.enabled,
+            params: conf
+                .strip_params
+                .iter()
+                .filter_map(|p| compile(p))
+                .collect(),
+            redirects: conf
+                .redirects
+                .iter()
+                .filter_map(|r| compile(&r.host).map(|h| (h, r.path.clone(), r.param.clone())))
+                .collect(),
+        }
+    }
+
+    pub fn clean(&self, url: &str) -> String {
+        if !self.enabled {
+            return url.to_string();
+        }
+        let mut current = url.trim().to_string();
+        // wrappers can be nested, but never follow more than a few levels
+        for _ in 0..5 {
+            let parts = match split(&current) {
+                Some(p) => p,
+                None => return current,
+            };
+            match self.unwrap_redirect(&parts) {
+                Some(target) => current = target,
+                None => return self.normalize(&parts),
+            }
+        }
+        current
+    }
+
+    fn unwrap_redirect(&self, parts: &Parts) -> Option<String> {
+        let host = parts.host.to_lowercase();
+        self.redirects
+            .iter()
+            .filter(|(h, path, _)| h.is_match(&host) && (path.is_empty() || parts.path == *path))
+            .filter_map(|(_, _, param)| {
+                parts
+                    .params()
+                    .find(|(k, _)| k == param)
+                    .map(|(_, v)| percent_decode(&v))
+            })
+            .find(|target| split(target).is_some())
+    }
+
+    fn normalize(&self, parts: &Parts) -> String {
+        let scheme = parts.scheme.to_lowercase();
+        let host = normalize_host(&scheme, &parts.host);
+        let params: Vec<String> = parts
+            .query
+            .split('&')
+            .filter(|p| !p.is_empty())
+            .filter(|p| {
+                let key = p.split('=').next().unwrap_or("");
+                !self.params.iter().any(|r| r.is_match(key))
+            })
+            .map(|p| p.to_string())
+            .collect();
+
+        let mut out = format!("{}://{}{}", scheme, host, parts.path);
+        if !params.is_empty() {
+            out.push('?');
+            out.push_str(&params.join("&"));
+        }
+        if let Some(ref fragment) = parts.fragment {
+            out.push('#');
+            out.push_str(fragment);
+        }
+        out
+    }
+}
+
+struct Parts {
+    scheme: String,
+    host: String,
+    path: String,
+    query: String,
+    fragment: Option<String>,
+}
+impl Parts {
+    fn params<'a>(&'a self) -> impl Iterator<Item = (String, String)> + 'a {
+        self.query.split('&').filter(|p| !p.is_empty()).map(|p| {
+            let mut kv = p.splitn(2, '=');
+            (
+                kv.next().unwrap_or("").to_string(),
+                kv.next().unwrap_or("").to_string(),
+            )
+        })
+    }
+}
+
+fn split(url: &str) -> Option<Parts> {
+    lazy_static! {
+        static ref URL: Regex =
+            Regex::new(r"^([a-zA-Z][a-zA-Z0-9+.-]*)://([^/?#]*)([^?#]*)(?:\?([^#]*))?(?:#(.*))?$")
+                .unwrap();
+    }
+    URL.captures(url).map(|c| Parts {
+        scheme: c[1].to_string(),
+        host: c[2].to_string(),
+        path: c[3].to_string(),
+        query: c.get(4).map(|m| m.as_str().to_string()).unwrap_or_default(),
+        fragment: c
+            .get(5)
+            .map(|m| m.as_str().to_string())
+            .filter(|f| !f.is_empty()),
+    })
+}
+
+fn normalize_host(scheme: &str, authority: &str) -> String {
+    let (userinfo, host) = match authority.rfind('@') {
+        Some(i) => (&authority[..=i], &authority[i + 1..]),
+        None => ("", authority),
+    };
+    let mut host = host.to_lowercase();
+    let default_port = match scheme {
+        "http" => Some(":80"),
+        "https" => Some(":443"),
+        _ => None,
+    };
+    if let Some(port) = default_port {
+        if host.ends_with(port) {
+            let len = host.len() - port.len();
+            host.truncate(len);
+        }
+    }
+    format!("{}{}", userinfo, host)
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                (Some(h), Some(l)) => {
+                    out.push(h << 4 | l);
+                    i += 2;
+                }
+                _ => out.push(b'%'),
+            },
+            b'+' => out.push(b' '),
+            b => out.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn hex(b: u8) -> Option<u8> {
+    (b as char).to_digit(16).map(|d| d as u8)
+}
+
+fn compile(pattern: &str) -> Option<Regex> {
+    match Regex::new(pattern) {
+        Ok(r) => Some(r),
+        Err(e) => {
+            warn!("ignoring invalid url cleaning pattern '{}': {}", pattern, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clean(url: &str) -> String {
+        UrlCleaner::new(&UrlCleaning::default()).clean(url)
+    }
+
+    #[test]
+    fn unwraps_redirects() {
+        assert_eq!(
+            clean("https://www.google.com/url?sa=t&q=https%3A%2F%2Fexample.com%2Fa%3Fb%3D1&usg=x"),
+            "https://example.com/a?b=1"
+        );
+        assert_eq!(
+            clean("https://l.facebook.com/l.php?u=http%3A%2F%2Fexample.com%2F&h=AT0"),
+            "http://example.com/"
+        );
+        // nested wrappers are unwrapped in turn
+        assert_eq!(
+            clean("https://out.reddit.com/t3?url=https%3A%2F%2Fwww.google.de%2Furl%3Fq%3Dhttps%253A%252F%252Fexample.com%252F"),
+            "https://example.com/"
+        );
+        // the wrapper is kept when the path or the target doesn't fit
+        assert_eq!(
+            clean("https://www.google.com/search?q=https%3A%2F%2Fexample.com"),
+            "https://www.google.com/search?q=https%3A%2F%2Fexample.com"
+        );
+        assert_eq!(
+            clean("https://www.google.com/url?q=not+a+url"),
+            "https://www.google.com/url?q=not+a+url"
+        );
+    }
+
+    #[test]
+    fn strips_tracking_params() {
+        assert_eq!(
+            clean("https://example.com/post?utm_source=rss&id=3&fbclid=abc&utm_medium=feed#top"),
+            "https://example.com/post?id=3#top"
+        );
+        assert_eq!(
+            clean("https://example.com/?mc_cid=1&mc_eid=2"),
+            "https://example.com/"
+        );
+        // only whole keys match, not ones that merely contain them
+        assert_eq!(
+            clean("https://example.com/?my_utm_source=1&gclid_x=2"),
+            "https://example.com/?my_utm_source=1&gclid_x=2"
+        );
+    }
+
+    #[test]
+    fn normalizes_scheme_and_host() {
+        assert_eq!(
+            clean("HTTPS://User@Example.COM:443/Path"),
+            "https://User@example.com/Path"
+        );
+        assert_eq!(
+            clean("http://example.com:8080/"),
+            "http://example.com:8080/"
+        );
+        assert_eq!(clean("not a url"), "not a url");
+    }
+
+    #[test]
+    fn disabled() {
+        let conf = UrlCleaning {
+            enabled: false,
+            ..Default::default()
+        };
+        let url = "https://example.com/?utm_source=rss";
+        assert_eq!(UrlCleaner::new(&conf).clean(url), url);
+    }
+}
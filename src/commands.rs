@@ -0,0 +1,464 @@
+use std::cmp::Ordering;
+use std::io::{self, IsTerminal};
+
+use folders::{split_folder, Folder};
+use images::localize;
+use podcast::size;
+use prune::{expired_items, remove_items};
+use query::{inherited, Context, Query};
+use rules::Rules;
+use store::{
+    feed_names, feed_tags, find_item, load_feed, load_items, mark_item_as_read, save_feed,
+    save_item, set_item_starred,
+};
+use types::{Episode, Feed, Item};
+use update::fetch_items;
+use {CLEANER, CONFIG, SCORER};
+
+const STARRED: &str = "starred";
+
+struct Row {
+    name: String,
+    title: String,
+    tags: Vec<String>,
+    unseen: usize,
+    total: usize,
+}
+
+pub fn list(folder: Option<&str>, depth: Option<usize>, tag: Option<&str>) {
+    let mut root = Folder::new();
+    for conf in &CONFIG.feeds {
+        let tags = feed_tags(conf);
+        if !has_tag(tag, &tags) {
+            continue;
+        }
+        let name = conf.name();
+        let items: Vec<Item> = load_items(&name)
+            .into_iter()
+            .filter(|i| !i.hidden)
+            .collect();
+        let title = match load_feed(&name) {
+            Some(ref f) if f.error.is_some() => format!("(error: {})", f.error.as_ref().unwrap()),
+            Some(Feed {
+                title,
+                warning: Some(w),
+                ..
+            }) => format!("{} (warning: {})", title, w),
+            Some(f) => f.title,
+            None => "(never updated)".to_string(),
+        };
+        let row = Row {
+            name,
+            title,
+            tags,
+            unseen: items.iter().filter(|i| !i.seen).count(),
+            total: items.len(),
+        };
+        root.insert(&conf.folder_path(), row);
+    }
+    let start = match folder {
+        Some(f) => match root.find(&split_folder(f)) {
+            Some(f) => f,
+            None => {
+                error!("no such folder: {}", f);
+                return;
+            }
+        },
+        None => &root,
+    };
+    print_folder(start, 0, depth);
+    if folder.is_some() {
+        return;
+    }
+    let items = all_items(tag);
+    let starred: Vec<&Item> = items.iter().filter(|i| i.starred).collect();
+    let unseen = starred.iter().filter(|i| !i.seen).count();
+    println!(
+        "{:>4}/{:<4} {:<20} (starred items)",
+        unseen,
+        starred.len(),
+        STARRED
+    );
+    let feeds = inherited();
+    for q in &CONFIG.queries {
+        let query = match Query::parse(&q.query) {
+            Ok(query) => query,
+            Err(e) => {
+                println!("{:>9} {:<20} (invalid query: {})", "", q.name, e);
+                continue;
+            }
+        };
+        let matched: Vec<&Item> = items
+            .iter()
+            .filter(|i| query.matches(&Context::new(i, &feeds)))
+            .collect();
+        let unseen = matched.iter().filter(|i| !i.seen).count();
+        println!(
+            "{:>4}/{:<4} {:<20} ({})",
+            unseen,
+            matched.len(),
+            q.name,
+            q.query
+        );
+    }
+}
+
+// folders deeper than `depth` are shown collapsed, with the counts of
+// everything below them
+fn print_folder(folder: &Folder<Row>, level: usize, depth: Option<usize>) {
+    let indent = "  ".repeat(level);
+    for (name, child) in &folder.children {
+        let (unseen, total) = child.totals(&|r: &Row| (r.unseen, r.total));
+        println!("{:>4}/{:<4} {}{}/", unseen, total, indent, name);
+        if depth.is_none_or(|d| level < d) {
+            print_folder(child, level + 1, depth);
+        }
+    }
+    for row in &folder.feeds {
+        println!(
+            "{:>4}/{:<4} {}{:<20} {}{}",
+            row.unseen,
+            row.total,
+            indent,
+            row.name,
+            printable(&row.title),
+            format_tags(&row.tags)
+        );
+    }
+}
+
+pub fn show(feed: Option<&str>, all: bool, tag: Option<&str>, query: Option<&str>, by_score: bool) {
+    let query = match query.map(Query::parse) {
+        Some(Ok(q)) => Some(q),
+        Some(Err(e)) => {
+            error!("invalid query: {}", e);
+            return;
+        }
+        None => None,
+    };
+    let mut items: Vec<Item> = match feed {
+        // starred items stay listed whether they have been read or not
+        Some(STARRED) => all_items(tag).into_iter().filter(|i| i.starred).collect(),
+        Some(name) if CONFIG.find_query(name).is_some() => {
+            let q = CONFIG.find_query(name).unwrap();
+            let query = match Query::parse(&q.query) {
+                Ok(query) => query,
+                Err(e) => {
+                    error!("invalid query for {}: {}", name, e);
+                    return;
+                }
+            };
+            let feeds = inherited();
+            all_items(tag)
+                .into_iter()
+                .filter(|i| (all || !i.seen) && query.matches(&Context::new(i, &feeds)))
+                .collect()
+        }
+        Some(name) => {
+            let feeds = match feeds_named(name) {
+                Some(f) => f,
+                None => {
+                    error!("no such feed or folder: {}", name);
+                    return;
+                }
+            };
+            all_items(tag)
+                .into_iter()
+                .filter(|i| feeds.contains(&i.feed) && (all || !i.seen))
+                .collect()
+        }
+        None => all_items(tag)
+            .into_iter()
+            .filter(|i| all || !i.seen)
+            .collect(),
+    };
+    if let Some(query) = query {
+        let feeds = inherited();
+        items.retain(|i| query.matches(&Context::new(i, &feeds)));
+    }
+    let mut scored: Vec<(i64, Item)> = items.into_iter().map(|i| (SCORER.score(&i), i)).collect();
+    scored.sort_by(|(sa, a), (sb, b)| match by_score {
+        true => sb.cmp(sa).then_with(|| newest_first(a, b)),
+        false => newest_first(a, b),
+    });
+    for (score, item) in scored {
+        print_summary(&item, score);
+    }
+}
+
+pub fn read(id: &str) {
+    let mut item = match find_item(id) {
+        Some(i) => i,
+        None => {
+            error!("no such item: {}", id);
+            return;
+        }
+    };
+    println!("{}", printable(&item.title));
+    println!("{}", printable(&CLEANER.clean(&item.link)));
+    if let Some(ref author) = item.author {
+        println!("by {}", printable(author));
+    }
+    if let Some(date) = item.published_at {
+        println!("{}", date.format("%Y-%m-%d %H:%M"));
+    }
+    if !item.tags.is_empty() {
+        println!("tags: {}", printable(&item.tags.join(", ")));
+    }
+    if let Some(ref t) = item.thumbnail {
+        println!("thumbnail: {}", printable(t));
+    }
+    if let Some(ref ep) = item.episode {
+        print_episode(ep);
+    }
+    for enc in &item.enclosures {
+        let details: Vec<String> = enc
+            .mime_type
+            .iter()
+            .cloned()
+            .chain(enc.length.map(size))
+            .chain(enc.duration.iter().cloned())
+            .collect();
+        let url = printable(&enc.url);
+        match details.is_empty() {
+            true => println!("enclosure: {}", url),
+            false => println!("enclosure: {} ({})", url, printable(&details.join(", "))),
+        }
+    }
+    println!();
+    let transcript = item.episode.as_ref().and_then(|e| e.transcript.as_ref());
+    match item.content.as_ref().or(item.summary.as_ref()) {
+        Some(body) if CONFIG.images.enabled => {
+            println!("{}", printable(&localize(body, &item.link)))
+        }
+        Some(body) => println!("{}", printable(body)),
+        None if transcript.is_some() => (),
+        None => println!("(no content)"),
+    }
+    if let Some(text) = transcript {
+        println!("\ntranscript:\n{}", printable(text));
+    }
+    mark_item_as_read(&mut item);
+}
+
+// one url per line, to hand to a media player
+pub fn media(id: &str) {
+    match find_item(id) {
+        Some(item) if item.enclosures.is_empty() => error!("{} has no media", id),
+        Some(item) => item
+            .enclosures
+            .iter()
+            .for_each(|e| println!("{}", printable(&e.url))),
+        None => error!("no such item: {}", id),
+    }
+}
+
+fn print_episode(ep: &Episode) {
+    match (ep.season, ep.episode) {
+        (Some(s), Some(e)) => println!("season {}, episode {}", s, e),
+        (None, Some(e)) => println!("episode {}", e),
+        (Some(s), None) => println!("season {}", s),
+        (None, None) => (),
+    }
+    if let Some(ref d) = ep.duration {
+        println!("duration: {}", printable(d));
+    }
+    if let Some(ref i) = ep.image {
+        println!("image: {}", printable(i));
+    }
+    if let Some(ref c) = ep.chapters {
+        println!("chapters: {}", printable(c));
+    }
+    for t in &ep.transcripts {
+        let details: Vec<&str> = t
+            .mime_type
+            .iter()
+            .chain(t.language.iter())
+            .map(|s| s.as_str())
+            .collect();
+        let url = printable(&t.url);
+        match details.is_empty() {
+            true => println!("transcript: {}", url),
+            false => println!("transcript: {} ({})", url, printable(&details.join(", "))),
+        }
+    }
+}
+
+pub fn star(id: &str, starred: bool) {
+    match find_item(id) {
+        Some(mut item) => set_item_starred(&mut item, starred),
+        None => error!("no such item: {}", id),
+    }
+}
+
+pub fn tag(target: &str, tags: Vec<&str>, remove: bool) {
+    if let Some(conf) = CONFIG.find_feed(target) {
+        let mut feed = load_feed(target).unwrap_or_else(|| Feed {
+            name: conf.name(),
+            url: conf.url.clone(),
+            ..Default::default()
+        });
+        edit_tags(&mut feed.tags, &tags, remove);
+        save_feed(&feed);
+        if remove {
+            tags.iter()
+                .filter(|t| conf.tags.iter().any(|c| c == *t))
+                .for_each(|t| warn!("tag '{}' is set in the config for {}", t, target));
+        }
+        return;
+    }
+    match find_item(target) {
+        Some(mut item) => {
+            edit_tags(&mut item.tags, &tags, remove);
+            save_item(&item);
+        }
+        None => error!("no such feed or item: {}", target),
+    }
+}
+
+// a dry run of the rules against what the feed currently serves
+pub fn test_rules(feed: &str) {
+    let conf = match CONFIG.find_feed(feed) {
+        Some(c) => c,
+        None => {
+            error!("no such feed: {}", feed);
+            return;
+        }
+    };
+    let items = match fetch_items(conf) {
+        Ok((_, items)) => items,
+        Err(e) => {
+            error!("could not fetch {}: {}", feed, e);
+            return;
+        }
+    };
+    let rules = Rules::for_feed(conf);
+    let mut matched = 0;
+    for item in &items {
+        let matching = rules.matching(item);
+        if !matching.is_empty() {
+            matched += 1;
+        }
+        for rule in matching {
+            println!(
+                "{:<5} {} {}  ({} =~ \"{}\")",
+                format!("{:?}", rule.action).to_lowercase(),
+                item.id,
+                printable(&item.title),
+                format!("{:?}", rule.field).to_lowercase(),
+                rule.pattern
+            );
+        }
+    }
+    println!("{} of {} items matched", matched, items.len());
+}
+
+pub fn prune(dry_run: bool) {
+    let mut total = 0;
+    for feed in feed_names() {
+        let expired = expired_items(&feed);
+        total += expired.len();
+        match dry_run {
+            true => expired.iter().for_each(|i| print_summary(i, 0)),
+            false => remove_items(&feed, &expired),
+        }
+    }
+    match dry_run {
+        true => println!("{} items would be deleted", total),
+        false => println!("deleted {} items", total),
+    }
+}
+
+fn edit_tags(current: &mut Vec<String>, tags: &[&str], remove: bool) {
+    if remove {
+        current.retain(|c| !tags.contains(&c.as_str()));
+    } else {
+        current.extend(tags.iter().map(|t| t.to_string()));
+        current.sort();
+        current.dedup();
+    }
+}
+
+// a feed name, or a folder standing for every feed below it
+fn feeds_named(name: &str) -> Option<Vec<String>> {
+    if let Some(conf) = CONFIG.find_feed(name) {
+        return Some(vec![conf.name()]);
+    }
+    // an empty path would stand for every feed
+    let path = split_folder(name);
+    if path.is_empty() {
+        return None;
+    }
+    Some(
+        CONFIG
+            .feeds
+            .iter()
+            .filter(|f| f.folder_path().starts_with(&path))
+            .map(|f| f.name())
+            .collect(),
+    )
+}
+
+fn all_items(tag: Option<&str>) -> Vec<Item> {
+    let feeds = inherited();
+    CONFIG
+        .feeds
+        .iter()
+        .flat_map(|f| load_items(&f.name()))
+        .filter(|i| !i.hidden)
+        .filter(|i| tag.is_none() || Context::new(i, &feeds).tags.contains(&tag.unwrap()))
+        .collect()
+}
+
+fn has_tag(tag: Option<&str>, tags: &[String]) -> bool {
+    match tag {
+        Some(t) => tags.iter().any(|x| x == t),
+        None => true,
+    }
+}
+
+fn format_tags(tags: &[String]) -> String {
+    match tags.is_empty() {
+        true => String::new(),
+        false => format!(" [{}]", tags.join(", ")),
+    }
+}
+
+fn print_summary(item: &Item, score: i64) {
+    let date = match item.published_at {
+        Some(d) => d.format("%Y-%m-%d").to_string(),
+        None => "----------".to_string(),
+    };
+    let seen = if item.seen { ' ' } else { 'N' };
+    let starred = if item.starred { '*' } else { ' ' };
+    let shown = match score {
+        0 => String::new(),
+        s => format!(" ({:+})", s),
+    };
+    let line = format!(
+        "{} {}{} {} [{}] {}{}",
+        item.id,
+        seen,
+        starred,
+        date,
+        item.feed,
+        printable(&item.title),
+        shown
+    );
+    match SCORER.is_highlighted(score) && io::stdout().is_terminal() {
+        true => println!("\x1b[1m{}\x1b[0m", line),
+        false => println!("{}", line),
+    }
+}
+
+// what feeds send is printed as text, so an escape sequence in it must not
+// reach the terminal; line breaks and tabs are all the control it gets
+pub fn printable(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control() || *c == '\n' || *c == '\t')
+        .collect()
+}
+
+fn newest_first(a: &Item, b: &Item) -> Ordering {
+    b.published_at.cmp(&a.published_at)
+}
@@ -0,0 +1,296 @@
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use regex::Regex;
+
+// formats tried, in order, once a date has been normalized to English
+// month names and spaces between fields, with the zone taken out
+const TIMES: &[&str] = &[
+    "%d %b %Y %H:%M:%S",
+    "%d %b %Y %H:%M",
+    "%b %d %Y %H:%M:%S",
+    "%b %d %Y %H:%M",
+    "%b %d %H:%M:%S %Y",
+    "%d %b %Y %I:%M:%S %p",
+    "%d %b %Y %I:%M %p",
+    "%b %d %Y %I:%M:%S %p",
+    "%b %d %Y %I:%M %p",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%d %H:%M",
+    "%Y/%m/%d %H:%M:%S",
+    "%Y/%m/%d %H:%M",
+];
+const DAYS: &[&str] = &["%d %b %Y", "%b %d %Y", "%Y-%m-%d", "%Y/%m/%d", "%d.%m.%Y"];
+
+// month names as publishers write them: English, French, German, Spanish,
+// Italian, Dutch and Portuguese
+const MONTHS: [&str; 12] = [
+    "jan january janv janvier januar jän jänner ene enero gen gennaio januari janeiro",
+    "feb february févr fevr février fevrier februar febrero febbraio februari fev fevereiro",
+    "mar march mars mär märz maerz marzo maart mrt março marco",
+    "apr april avr avril abr abril aprile",
+    "may mai mayo mag maggio mei maio",
+    "jun june juin juni junio giu giugno junho",
+    "jul july juil juillet juli julio lug luglio julho",
+    "aug august août aout ago agosto augustus",
+    "sep sept september septembre septiembre setiembre set settembre setembro",
+    "oct october octobre okt oktober octubre ott ottobre out outubro",
+    "nov november novembre noviembre",
+    "dec december déc décembre decembre dez dezember dic diciembre dicembre dezembro",
+];
+const ENGLISH: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+// weekdays carry no information and only get in the way, nor do these fillers
+const NOISE: &str = "mon monday tue tues tuesday wed wednesday thu thur thurs thursday \
+    fri friday sat saturday sun sunday lundi mardi mercredi jeudi vendredi samedi dimanche \
+    montag dienstag mittwoch donnerstag freitag samstag sonntag lunes martes miércoles \
+    jueves viernes sábado domingo at de del um à le der den";
+
+// named zones in minutes east of UTC
+const ZONES: &[(&str, i32)] = &[
+    ("z", 0),
+    ("ut", 0),
+    ("utc", 0),
+    ("gmt", 0),
+    ("wet", 0),
+    ("west", 60),
+    ("bst", 60),
+    ("cet", 60),
+    ("cest", 120),
+    ("met", 60),
+    ("mest", 120),
+    ("eet", 120),
+    ("eest", 180),
+    ("msk", 180),
+    ("ist", 330),
+    ("sgt", 480),
+    ("hkt", 480),
+    ("awst", 480),
+    ("jst", 540),
+    ("kst", 540),
+    ("acst", 570),
+    ("aest", 600),
+    ("aedt", 660),
+    ("nzst", 720),
+    ("nzdt", 780),
+    ("ast", -240),
+    ("adt", -180),
+    ("est", -300),
+    ("edt", -240),
+    ("cst", -360),
+    ("cdt", -300),
+    ("mst", -420),
+    ("mdt", -360),
+    ("pst", -480),
+    ("pdt", -420),
+    ("akst", -540),
+    ("akdt", -480),
+    ("hst", -600),
+];
+
+pub fn parse_date(date: &str) -> Option<DateTime<Utc>> {
+    let date = date.trim();
+    if let Ok(d) = DateTime::parse_from_rfc2822(date) {
+        return Some(d.with_timezone(&Utc));
+    }
+    if let Ok(d) = date.parse::<DateTime<Utc>>() {
+        return Some(d);
+    }
+    iso8601(date).or_else(|| lenient(date)).map(fix_year)
+}
+
+// tries the standard formats, then each of `formats` in chrono's strftime
+// syntax; dates without a timezone are taken as UTC, without a time as midnight
+pub fn parse_date_with(date: &str, formats: &[String]) -> Option<DateTime<Utc>> {
+    let date = date.trim();
+    parse_date(date).or_else(|| formats.iter().find_map(|f| with_format(date, f)))
+}
+
+fn with_format(date: &str, format: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_str(date, format)
+        .map(|d| d.with_timezone(&Utc))
+        .or_else(|_| NaiveDateTime::parse_from_str(date, format).map(|d| d.and_utc()))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(date, format)
+                .map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc())
+        })
+        .ok()
+}
+
+// basic and extended forms, optional seconds, fractions and zone
+fn iso8601(date: &str) -> Option<DateTime<Utc>> {
+    lazy_static! {
+        static ref ISO: Regex = Regex::new(
+            r"^(\d{4})-?(\d{2})-?(\d{2})(?:[T ](\d{2}):?(\d{2})(?::?(\d{2})(?:[.,]\d+)?)?)?\s*(Z|[+-]\d{2}(?::?\d{2})?)?$"
+        )
+        .unwrap();
+    }
+    let c = ISO.captures(date)?;
+    let num = |i: usize| c.get(i).map_or(Some(0), |m| m.as_str().parse::<u32>().ok());
+    let day = NaiveDate::from_ymd_opt(num(1)? as i32, num(2)?, num(3)?)?;
+    let time = day.and_hms_opt(num(4)?, num(5)?, num(6)?)?;
+    let offset = match c.get(7).map(|m| m.as_str()) {
+        None | Some("Z") => 0,
+        Some(z) => offset_minutes(z)?,
+    };
+    local(time, offset)
+}
+
+fn lenient(date: &str) -> Option<DateTime<Utc>> {
+    let (normalized, offset) = normalize(date);
+    TIMES
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(&normalized, f).ok())
+        .or_else(|| {
+            DAYS.iter()
+                .find_map(|f| NaiveDate::parse_from_str(&normalized, f).ok())
+                .and_then(|d| d.and_hms_opt(0, 0, 0))
+        })
+        .and_then(|t| local(t, offset.unwrap_or(0)))
+}
+
+// rewrites the date into words chrono understands, pulling out the zone
+fn normalize(date: &str) -> (String, Option<i32>) {
+    lazy_static! {
+        static ref ORDINAL: Regex = Regex::new(r"^(\d{1,2})(?:st|nd|rd|th|er|e|º)$").unwrap();
+        static ref OFFSET: Regex =
+            Regex::new(r"^(?:(?:gmt|utc)?([+-]\d{1,2}(?::?\d{2})?))$").unwrap();
+    }
+    let mut words = vec![];
+    let mut offset = None;
+    for token in date.split(|c: char| c.is_whitespace() || c == ',') {
+        let t = token.trim_end_matches('.').to_lowercase();
+        if t.is_empty() || NOISE.split_whitespace().any(|n| n == t) {
+            continue;
+        }
+        // times glued to their zone, as in 10:00:00+0200
+        if let Some(i) = t
+            .rfind(['+', '-'])
+            .filter(|i| *i > 0 && t[..*i].contains(':'))
+        {
+            if let Some(o) = offset_minutes(&t[i..]) {
+                offset = Some(o);
+                words.push(t[..i].to_string());
+                continue;
+            }
+        }
+        if let Some(m) = MONTHS
+            .iter()
+            .position(|names| names.split_whitespace().any(|n| n == t))
+        {
+            words.push(ENGLISH[m].to_string());
+        } else if let Some(&(_, o)) = ZONES.iter().find(|(name, _)| *name == t) {
+            offset = Some(o);
+        } else if let Some(o) = OFFSET.captures(&t).and_then(|c| offset_minutes(&c[1])) {
+            offset = Some(o);
+        } else if let Some(c) = ORDINAL.captures(&t) {
+            words.push(c[1].to_string());
+        } else {
+            words.push(t);
+        }
+    }
+    (words.join(" "), offset)
+}
+
+// +02, +0200 or +02:00
+fn offset_minutes(z: &str) -> Option<i32> {
+    let sign = match z.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits: String = z[1..].chars().filter(|c| *c != ':').collect();
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let (h, m) = match digits.len() {
+        1 | 2 => (digits.parse::<i32>().ok()?, 0),
+        4 => (
+            digits[..2].parse::<i32>().ok()?,
+            digits[2..].parse::<i32>().ok()?,
+        ),
+        _ => return None,
+    };
+    Some(sign * (h * 60 + m))
+}
+
+fn local(time: NaiveDateTime, offset: i32) -> Option<DateTime<Utc>> {
+    FixedOffset::east_opt(offset * 60)?
+        .from_local_datetime(&time)
+        .single()
+        .map(|d| d.with_timezone(&Utc))
+}
+
+// two-digit years read as years of the first century
+fn fix_year(d: DateTime<Utc>) -> DateTime<Utc> {
+    match d.year() {
+        y @ 0..=69 => d.with_year(y + 2000).unwrap_or(d),
+        y @ 70..=99 => d.with_year(y + 1900).unwrap_or(d),
+        _ => d,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(date: &str) -> String {
+        parse_date(date).unwrap().to_rfc3339()
+    }
+
+    #[test]
+    fn named_zones() {
+        assert_eq!(
+            utc("Tue, 10 Jun 2003 04:00:00 CEST"),
+            "2003-06-10T02:00:00+00:00"
+        );
+        assert_eq!(utc("June 10 2003 9:30 PM PDT"), "2003-06-11T04:30:00+00:00");
+        assert_eq!(
+            utc("10 Jun 2003 04:00:00 GMT+05:30"),
+            "2003-06-09T22:30:00+00:00"
+        );
+        assert_eq!(utc("10 Jun 2003 04:00:00 IST"), "2003-06-09T22:30:00+00:00");
+    }
+
+    #[test]
+    fn two_digit_years() {
+        assert_eq!(utc("5 March 24"), "2024-03-05T00:00:00+00:00");
+        assert_eq!(utc("Mar 5 99 10:00"), "1999-03-05T10:00:00+00:00");
+        assert_eq!(
+            utc("Sun, 05 Mar 00 10:00:00 +0000"),
+            "2000-03-05T10:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn localized_months() {
+        assert_eq!(utc("3 février 2021"), "2021-02-03T00:00:00+00:00");
+        assert_eq!(
+            utc("Montag, 15. März 2020 10:30"),
+            "2020-03-15T10:30:00+00:00"
+        );
+        assert_eq!(
+            utc("lunes, 7 de junio de 2021"),
+            "2021-06-07T00:00:00+00:00"
+        );
+        assert_eq!(utc("1º gennaio 2020"), "2020-01-01T00:00:00+00:00");
+        assert_eq!(utc("12 okt. 2019 08:00 CEST"), "2019-10-12T06:00:00+00:00");
+    }
+
+    #[test]
+    fn iso_forms() {
+        assert_eq!(utc("20030610T0400+0200"), "2003-06-10T02:00:00+00:00");
+        assert_eq!(utc("2003-06-10 04:00"), "2003-06-10T04:00:00+00:00");
+    }
+
+    #[test]
+    fn custom_formats() {
+        let formats = vec!["%d|%m|%Y".to_string()];
+        assert_eq!(
+            parse_date_with("10|06|2003", &formats).map(|d| d.to_rfc3339()),
+            Some("2003-06-10T00:00:00+00:00".to_string())
+        );
+        assert_eq!(parse_date_with("10|06|2003", &[]), None);
+        assert_eq!(parse_date("yesterday"), None);
+    }
+}
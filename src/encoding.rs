@@ -0,0 +1,147 @@
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE};
+use regex::bytes::Regex;
+
+// how far into a document to look for a declared encoding
+const PROLOG: usize = 1024;
+
+// returns the document as UTF-8, going by its BOM, the charset from the
+// Content-Type header, the XML declaration or HTML meta tag, and otherwise
+// guessing; text that is already valid UTF-8 is kept whatever it claims to be
+pub fn to_utf8(data: Vec<u8>, content_type: Option<&str>) -> Vec<u8> {
+    if let Some((encoding, bom)) = Encoding::for_bom(&data) {
+        debug!("found a {} byte order mark", encoding.name());
+        return decode(encoding, &data[bom..]);
+    }
+    let declared = content_type
+        .and_then(charset)
+        .or_else(|| declared(&data))
+        .and_then(|label| Encoding::for_label(label.as_bytes()))
+        .or_else(|| utf16_prolog(&data));
+    match declared {
+        // utf-16 can pass for valid utf-8 when it's mostly ascii
+        Some(e) if e == UTF_16LE || e == UTF_16BE => decode(e, &data),
+        _ if ::std::str::from_utf8(&data).is_ok() => data,
+        Some(e) if e != encoding_rs::UTF_8 => decode(e, &data),
+        _ => {
+            let mut detector = EncodingDetector::new();
+            detector.feed(&data, true);
+            let guess = detector.guess(None, false);
+            debug!("document is not utf-8, guessed {}", guess.name());
+            decode(guess, &data)
+        }
+    }
+}
+
+fn decode(encoding: &'static Encoding, data: &[u8]) -> Vec<u8> {
+    let (text, malformed) = encoding.decode_without_bom_handling(data);
+    if malformed {
+        warn!("document has bytes that are invalid in {}", encoding.name());
+    }
+    text.into_owned().into_bytes()
+}
+
+// utf-16 without a BOM can only be told by how `<?` is laid out, its
+// declaration can't be read as ascii
+fn utf16_prolog(data: &[u8]) -> Option<&'static Encoding> {
+    match data {
+        [b'<', 0, b'?', 0, ..] => Some(UTF_16LE),
+        [0, b'<', 0, b'?', ..] => Some(UTF_16BE),
+        _ => None,
+    }
+}
+
+// the charset parameter of a Content-Type header
+fn charset(content_type: &str) -> Option<String> {
+    content_type
+        .split(';')
+        .skip(1)
+        .filter_map(|p| p.split_once('='))
+        .find(|(k, _)| k.trim().eq_ignore_ascii_case("charset"))
+        .map(|(_, v)| v.trim().trim_matches('"').to_string())
+}
+
+fn declared(data: &[u8]) -> Option<String> {
+    lazy_static! {
+        static ref XML: Regex =
+            Regex::new(r#"^\s*<\?xml[^>]*encoding\s*=\s*["']([A-Za-z0-9._:-]+)["']"#).unwrap();
+        static ref META: Regex =
+            Regex::new(r#"(?i)<meta[^>]+charset\s*=\s*["']?([A-Za-z0-9._:-]+)"#).unwrap();
+    }
+    let prolog = &data[..data.len().min(PROLOG)];
+    XML.captures(prolog)
+        .or_else(|| META.captures(prolog))
+        .map(|c| String::from_utf8_lossy(&c[1]).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf8(data: &[u8], content_type: Option<&str>) -> String {
+        String::from_utf8(to_utf8(data.to_vec(), content_type)).unwrap()
+    }
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(|c| c.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn byte_order_marks() {
+        assert_eq!(utf8(b"\xef\xbb\xbfcaf\xc3\xa9", None), "café");
+        let mut data = vec![0xff, 0xfe];
+        data.extend(utf16le("café"));
+        // the BOM wins over whatever is declared
+        assert_eq!(utf8(&data, Some("text/xml; charset=iso-8859-1")), "café");
+    }
+
+    #[test]
+    fn header_over_declaration() {
+        let data = b"<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><t>\xb1</t>";
+        assert_eq!(
+            utf8(data, Some("application/rss+xml; charset=\"ISO-8859-2\"")),
+            "<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><t>ą</t>"
+        );
+        assert_eq!(
+            utf8(data, Some("application/rss+xml")),
+            "<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><t>±</t>"
+        );
+        let html = b"<html><head><meta charset=\"koi8-r\"></head>\xf0\xd2\xc9\xd7\xc5\xd4</html>";
+        assert_eq!(
+            utf8(html, None),
+            "<html><head><meta charset=\"koi8-r\"></head>Привет</html>"
+        );
+    }
+
+    #[test]
+    fn valid_utf8_beats_a_declaration() {
+        let data = "<?xml version=\"1.0\" encoding=\"ISO-8859-1\"?><t>café</t>";
+        assert_eq!(utf8(data.as_bytes(), None), data);
+        assert_eq!(
+            utf8(data.as_bytes(), Some("text/xml; charset=latin1")),
+            data
+        );
+    }
+
+    #[test]
+    fn undeclared_windows_1252() {
+        let data = b"<p>\x93Quoted\x94 caf\xe9 cr\xe8me br\xfbl\xe9e \x96 d\xe9j\xe0 vu</p>";
+        assert_eq!(
+            utf8(data, None),
+            "<p>\u{201c}Quoted\u{201d} café crème brûlée \u{2013} déjà vu</p>"
+        );
+    }
+
+    #[test]
+    fn utf16_without_bom() {
+        let xml = "<?xml version=\"1.0\" encoding=\"UTF-16\"?><t>hi</t>";
+        assert_eq!(utf8(&utf16le(xml), None), xml);
+        let be: Vec<u8> = xml.encode_utf16().flat_map(|c| c.to_be_bytes()).collect();
+        assert_eq!(utf8(&be, None), xml);
+        // mostly ascii utf-16 is valid utf-8 too, the header tells them apart
+        assert_eq!(
+            utf8(&utf16le("hi"), Some("text/plain; charset=utf-16le")),
+            "hi"
+        );
+    }
+}
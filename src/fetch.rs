@@ -0,0 +1,174 @@
+use libc;
+use std::io::{Read, Write};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+use ureq;
+use url::Url;
+
+// feeds supply urls too, so this must never reach a command or a file;
+// returns the body as sent, along with its Content-Type
+pub fn fetch(url: &str, timeout: Duration) -> Result<(Vec<u8>, Option<String>), String> {
+    match Url::parse(url) {
+        Ok(ref u) if u.scheme() == "http" || u.scheme() == "https" => (),
+        _ => return Err(format!("not an http(s) url: {}", url)),
+    }
+    let response = ureq::get(url)
+        .timeout(timeout)
+        .call()
+        .map_err(|e| e.to_string())?;
+    let content_type = response.header("content-type").map(|s| s.to_string());
+    let mut body = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut body)
+        .map_err(|e| e.to_string())?;
+    Ok((body, content_type))
+}
+
+// runs `command` through the shell and returns its stdout, failing on a
+// non-zero exit (with whatever it printed to stderr) or when it takes too long
+pub fn run_command(
+    command: &str,
+    input: Option<&[u8]>,
+    env: &[(&str, &str)],
+    timeout: Duration,
+) -> Result<Vec<u8>, String> {
+    // in a process group of its own, so that whatever it leaves running in
+    // the background can be killed along with it
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(env.iter().cloned())
+        .process_group(0)
+        .stdin(match input {
+            Some(_) => Stdio::piped(),
+            None => Stdio::null(),
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("could not run '{}': {}", command, e))?;
+
+    // the pipes are drained on their own threads so a chatty command can't
+    // block on a full buffer while we wait for it
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+        let input = input.to_vec();
+        thread::spawn(move || {
+            let _ = stdin.write_all(&input);
+        });
+    }
+    let (closed, pipes) = mpsc::channel();
+    let stdout = read_pipe(child.stdout.take(), closed.clone());
+    let stderr = read_pipe(child.stderr.take(), closed);
+
+    // it's done once it has exited and its pipes are closed, which a
+    // background child may keep open well after the shell is gone
+    let start = Instant::now();
+    let mut status = None;
+    let mut open = 2;
+    while status.is_none() || open > 0 {
+        if start.elapsed() >= timeout {
+            kill_group(&mut child);
+            return Err(format!(
+                "'{}' timed out after {} seconds",
+                command,
+                timeout.as_secs()
+            ));
+        }
+        if status.is_none() {
+            status = child
+                .try_wait()
+                .map_err(|e| format!("could not wait for '{}': {}", command, e))?;
+        }
+        open -= pipes.try_iter().count();
+        thread::sleep(Duration::from_millis(10));
+    }
+    let status = status.unwrap();
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr);
+        return Err(match stderr.trim() {
+            "" => format!("'{}' exited with {}", command, status),
+            s => format!("'{}' exited with {}: {}", command, status, s),
+        });
+    }
+    Ok(stdout)
+}
+
+fn read_pipe<R: Read + Send + 'static>(
+    pipe: Option<R>,
+    closed: mpsc::Sender<()>,
+) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut out = Vec::new();
+        if let Some(mut p) = pipe {
+            let _ = p.read_to_end(&mut out);
+        }
+        let _ = closed.send(());
+        out
+    })
+}
+
+fn kill_group(child: &mut Child) {
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+    let _ = child.wait();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(command: &str, input: Option<&[u8]>) -> Result<Vec<u8>, String> {
+        run_command(
+            command,
+            input,
+            &[("NAME", "mercury")],
+            Duration::from_secs(2),
+        )
+    }
+
+    #[test]
+    fn output_and_input() {
+        assert_eq!(run("echo $NAME", None).unwrap(), b"mercury\n");
+        assert_eq!(run("tr a-z A-Z", Some(b"hi")).unwrap(), b"HI");
+        assert_eq!(
+            run("echo oops >&2; exit 3", None).unwrap_err(),
+            "'echo oops >&2; exit 3' exited with exit status: 3: oops"
+        );
+    }
+
+    #[test]
+    fn times_out() {
+        let start = Instant::now();
+        assert!(run("sleep 10", None).is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn background_children_time_out_too() {
+        let start = Instant::now();
+        assert_eq!(
+            run("sleep 10 & echo hi", None).unwrap_err(),
+            "'sleep 10 & echo hi' timed out after 2 seconds"
+        );
+        assert!(start.elapsed() < Duration::from_secs(5));
+        // and are killed with the shell, rather than left running
+        let start = Instant::now();
+        assert!(run("(sleep 10; echo late) & sleep 10", None).is_err());
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn detached_children_are_left_alone() {
+        assert_eq!(
+            run("sleep 10 >/dev/null 2>&1 & echo hi", None).unwrap(),
+            b"hi\n"
+        );
+    }
+}
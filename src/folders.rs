@@ -0,0 +1,52 @@
+use std::collections::BTreeMap;
+
+pub struct Folder<T> {
+    pub children: BTreeMap<String, Folder<T>>,
+    pub feeds: Vec<T>,
+}
+impl<T> Folder<T> {
+    pub fn new() -> Self {
+        Folder {
+            children: BTreeMap::new(),
+            feeds: vec![],
+        }
+    }
+
+    pub fn insert(&mut self, path: &[String], feed: T) {
+        match path.split_first() {
+            Some((first, rest)) => self
+                .children
+                .entry(first.clone())
+                .or_insert_with(Folder::new)
+                .insert(rest, feed),
+            None => self.feeds.push(feed),
+        }
+    }
+
+    pub fn find(&self, path: &[String]) -> Option<&Folder<T>> {
+        match path.split_first() {
+            Some((first, rest)) => self.children.get(first).and_then(|c| c.find(rest)),
+            None => Some(self),
+        }
+    }
+
+    // sums the counts of every feed in this folder and all folders below it
+    pub fn totals<F>(&self, counts: &F) -> (usize, usize)
+    where
+        F: Fn(&T) -> (usize, usize),
+    {
+        let own = self.feeds.iter().map(counts);
+        let nested = self.children.values().map(|c| c.totals(counts));
+        own.chain(nested)
+            .fold((0, 0), |(a, b), (x, y)| (a + x, b + y))
+    }
+}
+
+pub fn split_folder(folder: &str) -> Vec<String> {
+    folder
+        .split('/')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_string())
+        .collect()
+}
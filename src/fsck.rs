@@ -0,0 +1,329 @@
+use chrono::Utc;
+use serde_json;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use images;
+use podcast::{size, Download};
+use store::{items_dir, load_queue, read_json, save_feed, save_item, save_queue, write_json};
+use types::{Feed, Item};
+use update::item_id;
+use CONFIG;
+
+// corrupt and stray files are moved here rather than deleted
+const QUARANTINE: &str = "quarantine";
+
+struct Fsck {
+    repair: bool,
+    problems: usize,
+    feeds: usize,
+    items: usize,
+    ids: HashSet<String>,
+}
+
+impl Fsck {
+    fn problem(&mut self, msg: String) {
+        self.problems += 1;
+        println!("{}", msg);
+    }
+
+    fn quarantine(&self, path: &Path) {
+        if !self.repair {
+            return;
+        }
+        let rel = path.strip_prefix(&CONFIG.feed_path).unwrap_or(path);
+        let mut dest = CONFIG.feed_path.join(QUARANTINE).join(rel);
+        if dest.exists() {
+            let mut name = dest.as_os_str().to_owned();
+            name.push(format!(".{}", Utc::now().timestamp()));
+            dest = PathBuf::from(name);
+        }
+        let moved = dest
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::rename(path, &dest));
+        match moved {
+            Ok(()) => println!("  moved to {}", dest.display()),
+            Err(e) => error!("could not move {}: {}", path.display(), e),
+        }
+    }
+}
+
+// checks that every record under feed_path can be read and agrees with
+// where it is stored, and with `repair` puts right what it can
+pub fn fsck(repair: bool) {
+    let mut check = Fsck {
+        repair,
+        problems: 0,
+        feeds: 0,
+        items: 0,
+        ids: HashSet::new(),
+    };
+    for path in entries(&CONFIG.feed_path) {
+        let name = file_name(&path);
+        // podcasts are downloaded into the store unless set otherwise
+        if path == CONFIG.podcasts.directory {
+            continue;
+        }
+        if path.is_dir() {
+            match name.as_str() {
+                QUARANTINE => (),
+                "images" => check_images(&mut check),
+                _ => check_feed(&mut check, &path, &name),
+            }
+        } else if name != "podcasts.json" {
+            check.problem(format!("{}: not part of the store", path.display()));
+            check.quarantine(&path);
+        }
+    }
+    check_queue(&mut check);
+    println!(
+        "checked {} feeds and {} items, found {} problems",
+        check.feeds, check.items, check.problems
+    );
+    if check.problems > 0 && !repair {
+        println!("run with --repair to fix them");
+    }
+}
+
+fn check_feed(check: &mut Fsck, dir: &Path, name: &str) {
+    check.feeds += 1;
+    let path = dir.join("feed.json");
+    let feed = match fs::read(&path) {
+        Ok(data) => match serde_json::from_slice::<Feed>(&data) {
+            Ok(f) => Some(f),
+            Err(e) => {
+                check.problem(format!("{}: {}", path.display(), e));
+                check.quarantine(&path);
+                None
+            }
+        },
+        Err(_) => {
+            check.problem(format!("{}: missing", path.display()));
+            None
+        }
+    };
+    match feed {
+        Some(mut f) if f.name != name => {
+            check.problem(format!("{}: names feed '{}'", path.display(), f.name));
+            if check.repair {
+                f.name = name.to_string();
+                save_feed(&f);
+            }
+        }
+        Some(_) => (),
+        // without one the items can't be listed, the next update fills it in
+        None if check.repair => save_feed(&Feed {
+            name: name.to_string(),
+            url: CONFIG
+                .find_feed(name)
+                .map(|c| c.url.clone())
+                .unwrap_or_default(),
+            title: name.to_string(),
+            ..Default::default()
+        }),
+        None => (),
+    }
+    if CONFIG.find_feed(name).is_none() {
+        println!("{}: not in the config", name);
+    }
+    for path in entries(dir) {
+        let file = file_name(&path);
+        if file != "feed.json" && file != "items" {
+            check.problem(format!("{}: not part of the store", path.display()));
+            check.quarantine(&path);
+        }
+    }
+    for path in entries(&items_dir(name)) {
+        check_item(check, &path, name);
+    }
+}
+
+fn check_item(check: &mut Fsck, path: &Path, feed: &str) {
+    let stem = match file_name(path).strip_suffix(".json") {
+        Some(s) => s.to_string(),
+        None => {
+            check.problem(format!("{}: not part of the store", path.display()));
+            return check.quarantine(path);
+        }
+    };
+    let mut item = match fs::read(path).map(|d| serde_json::from_slice::<Item>(&d)) {
+        Ok(Ok(i)) => i,
+        Ok(Err(e)) => {
+            check.problem(format!("{}: {}", path.display(), e));
+            return check.quarantine(path);
+        }
+        Err(e) => {
+            check.problem(format!("{}: {}", path.display(), e));
+            return;
+        }
+    };
+    check.items += 1;
+    let id = item_id(feed, &item.guid);
+    if item.id != stem || item.feed != feed || item.id != id {
+        check.problem(format!(
+            "{}: holds item {} of feed '{}'",
+            path.display(),
+            item.id,
+            item.feed
+        ));
+        if check.repair {
+            item.id = id.clone();
+            item.feed = feed.to_string();
+            if id == stem {
+                save_item(&item);
+            } else if items_dir(feed).join(format!("{}.json", id)).exists() {
+                // a copy of an item that is already stored
+                check.quarantine(path);
+            } else {
+                save_item(&item);
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+    check.ids.insert(id);
+}
+
+fn check_queue(check: &mut Fsck) {
+    let path = CONFIG.feed_path.join("podcasts.json");
+    let data = match fs::read(&path) {
+        Ok(d) => d,
+        Err(_) => return,
+    };
+    if let Err(e) = serde_json::from_slice::<Vec<Download>>(&data) {
+        check.problem(format!("{}: {}", path.display(), e));
+        return check.quarantine(&path);
+    }
+    // finished downloads are worth keeping track of without their item
+    let (queue, missing): (Vec<Download>, Vec<Download>) = load_queue()
+        .into_iter()
+        .partition(|d| d.done || check.ids.contains(&d.item));
+    for d in &missing {
+        check.problem(format!(
+            "{}: queues missing item {}",
+            path.display(),
+            d.item
+        ));
+    }
+    if check.repair && !missing.is_empty() {
+        save_queue(&queue);
+    }
+}
+
+fn check_images(check: &mut Fsck) {
+    let index_path = images::index_path();
+    let mut index: HashMap<String, String> = match fs::read(&index_path) {
+        Ok(data) => match serde_json::from_slice(&data) {
+            Ok(i) => i,
+            Err(e) => {
+                check.problem(format!("{}: {}", index_path.display(), e));
+                check.quarantine(&index_path);
+                HashMap::new()
+            }
+        },
+        Err(_) => HashMap::new(),
+    };
+    let before = index.len();
+    index.retain(|url, f| match images::cache_dir().join(f).exists() {
+        true => true,
+        false => {
+            check.problem(format!("{}: {} is missing", index_path.display(), url));
+            false
+        }
+    });
+    if check.repair && index.len() != before {
+        write_json(&index_path, &index);
+    }
+    let listed: HashSet<&String> = index.values().collect();
+    for path in entries(&images::cache_dir()) {
+        if path != index_path && !listed.contains(&file_name(&path)) {
+            check.problem(format!("{}: not in the image index", path.display()));
+            check.quarantine(&path);
+        }
+    }
+}
+
+// drops what the store collects over time: records left half written,
+// cached images nothing points to and finished downloads since deleted
+pub fn compact() {
+    let mut removed: Vec<(PathBuf, u64)> = vec![];
+    for path in files(&CONFIG.feed_path) {
+        if file_name(&path).ends_with(".json.tmp") {
+            removed.extend(remove(&path));
+        }
+    }
+
+    let index_path = images::index_path();
+    let mut index: HashMap<String, String> = read_json(&index_path).unwrap_or_default();
+    let before = index.len();
+    index.retain(|_, f| images::cache_dir().join(f).exists());
+    let listed: HashSet<String> = index.values().cloned().collect();
+    for path in entries(&images::cache_dir()) {
+        if path != index_path && !listed.contains(&file_name(&path)) {
+            removed.extend(remove(&path));
+        }
+    }
+    if index.len() != before {
+        write_json(&index_path, &index);
+    }
+
+    let mut queue = load_queue();
+    let before = queue.len();
+    queue.retain(|d| !d.done || d.path.exists());
+    if queue.len() != before {
+        info!(
+            "dropped {} deleted downloads from the queue",
+            before - queue.len()
+        );
+        save_queue(&queue);
+    }
+
+    for (path, _) in &removed {
+        debug!("removed {}", path.display());
+    }
+    println!(
+        "removed {} files, {} reclaimed",
+        removed.len(),
+        size(removed.iter().map(|r| r.1).sum())
+    );
+}
+
+fn remove(path: &Path) -> Option<(PathBuf, u64)> {
+    let len = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    match fs::remove_file(path) {
+        Ok(()) => Some((path.to_path_buf(), len)),
+        Err(e) => {
+            error!("could not remove {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn entries(dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+        Err(_) => vec![],
+    };
+    paths.sort();
+    paths
+}
+
+// every file below `dir`, leaving out the quarantine
+fn files(dir: &Path) -> Vec<PathBuf> {
+    let mut out = vec![];
+    for path in entries(dir) {
+        if path.is_dir() && file_name(&path) != QUARANTINE {
+            out.extend(files(&path));
+        } else if path.is_file() {
+            out.push(path);
+        }
+    }
+    out
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
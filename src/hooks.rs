@@ -0,0 +1,57 @@
+use serde_json;
+use std::time::Duration;
+
+use fetch::run_command;
+use types::Item;
+use CONFIG;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Hook {
+    pub command: String,
+    #[serde(default)]
+    pub batch: bool,
+    // seconds before the hook is killed
+    #[serde(default)]
+    pub timeout: Option<u64>,
+}
+impl Hook {
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout.unwrap_or(60))
+    }
+}
+
+// per-item hooks get the item as a JSON object on stdin; like
+// notifications they leave out items the rules hid
+pub fn run_item_hooks(items: &[Item]) {
+    for hook in CONFIG.hooks.iter().filter(|h| !h.batch) {
+        for item in items.iter().filter(|i| !i.hidden) {
+            let input = serde_json::to_vec(item).expect("could not serialize item");
+            run(hook, &input, &[("MERCURY_FEED", &item.feed)]);
+        }
+    }
+}
+
+// batch hooks get every item of an update as a JSON array, once
+pub fn run_batch_hooks(items: &[Item]) {
+    let items: Vec<&Item> = items.iter().filter(|i| !i.hidden).collect();
+    if items.is_empty() {
+        return;
+    }
+    for hook in CONFIG.hooks.iter().filter(|h| h.batch) {
+        let input = serde_json::to_vec(&items).expect("could not serialize items");
+        run(hook, &input, &[]);
+    }
+}
+
+// what a hook prints goes to the log rather than between our own output
+fn run(hook: &Hook, input: &[u8], env: &[(&str, &str)]) {
+    match run_command(&hook.command, Some(input), env, hook.timeout()) {
+        Ok(out) if out.is_empty() => (),
+        Ok(out) => debug!(
+            "hook '{}' printed: {}",
+            hook.command,
+            String::from_utf8_lossy(&out).trim()
+        ),
+        Err(e) => warn!("hook failed: {}", e),
+    }
+}
@@ -0,0 +1,218 @@
+use regex::{Captures, Regex};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use ureq;
+use url::Url;
+
+use store::{read_json, write_json};
+use types::Item;
+use CONFIG;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ImageCache {
+    #[serde(default)]
+    pub enabled: bool,
+    // in MiB
+    #[serde(default = "ImageCache::default_max_size")]
+    pub max_size: u64,
+}
+impl ImageCache {
+    fn default_max_size() -> u64 {
+        200
+    }
+}
+impl Default for ImageCache {
+    fn default() -> Self {
+        ImageCache {
+            enabled: false,
+            max_size: Self::default_max_size(),
+        }
+    }
+}
+
+pub fn cache_dir() -> PathBuf {
+    CONFIG.feed_path.join("images")
+}
+
+// image url to the file holding it, named by the hash of its contents so
+// the same image behind different urls is only stored once
+pub fn index_path() -> PathBuf {
+    cache_dir().join("index.json")
+}
+
+fn img_src() -> &'static Regex {
+    lazy_static! {
+        static ref SRC: Regex =
+            Regex::new(r#"(?i)(<img\b[^>]*?\bsrc\s*=\s*)(["'])([^"']+)["']"#).unwrap();
+    }
+    &SRC
+}
+
+// downloads the images in the items' content, then trims the cache
+pub fn cache_images(items: &[Item]) {
+    let mut index: HashMap<String, String> = read_json(&index_path()).unwrap_or_default();
+    let before = index.len();
+    for item in items {
+        let html = item.content.iter().chain(item.summary.iter());
+        for url in html.flat_map(|h| image_urls(h, &item.link)) {
+            if index
+                .get(&url)
+                .is_some_and(|f| cache_dir().join(f).exists())
+            {
+                continue;
+            }
+            match download(&url) {
+                Ok(file) => {
+                    index.insert(url, file);
+                }
+                Err(e) => warn!("could not cache {}: {}", url, e),
+            }
+        }
+    }
+    if index.len() != before {
+        debug!("cached {} images", index.len() - before);
+    }
+    evict(&mut index);
+    write_json(&index_path(), &index);
+}
+
+// points the images in `html` at their cached copies, marking them as used
+pub fn localize(html: &str, base: &str) -> String {
+    let index: HashMap<String, String> = match read_json(&index_path()) {
+        Some(i) => i,
+        None => return html.to_string(),
+    };
+    let base = Url::parse(base).ok();
+    img_src()
+        .replace_all(html, |c: &Captures| {
+            let cached = absolute(&c[3], base.as_ref())
+                .and_then(|u| index.get(&u))
+                .map(|f| cache_dir().join(f))
+                .filter(|p| p.exists())
+                .and_then(|p| file_src(&p).map(|src| (p, src)));
+            match cached {
+                Some((p, src)) => {
+                    // the modification time is what eviction goes by
+                    if let Ok(f) = File::options().append(true).open(&p) {
+                        let _ = f.set_modified(SystemTime::now());
+                    }
+                    format!("{}{}{}{}", &c[1], &c[2], src, &c[2])
+                }
+                None => c[0].to_string(),
+            }
+        })
+        .into_owned()
+}
+
+// feed_path may hold anything, spaces and quotes included
+fn file_src(path: &Path) -> Option<String> {
+    let url = Url::from_file_path(path).ok()?;
+    Some(
+        url.as_str()
+            .replace('&', "&amp;")
+            .replace('"', "&quot;")
+            .replace('\'', "&#39;"),
+    )
+}
+
+fn image_urls(html: &str, base: &str) -> Vec<String> {
+    let base = Url::parse(base).ok();
+    img_src()
+        .captures_iter(html)
+        .filter_map(|c| absolute(&c[3], base.as_ref()))
+        .collect()
+}
+
+// only http images are worth fetching, data: urls are already inline
+fn absolute(src: &str, base: Option<&Url>) -> Option<String> {
+    let src = src.replace("&amp;", "&");
+    let url = match base {
+        Some(b) => b.join(&src).ok()?,
+        None => Url::parse(&src).ok()?,
+    };
+    match url.scheme() {
+        "http" | "https" => Some(url.to_string()),
+        _ => None,
+    }
+}
+
+fn download(url: &str) -> Result<String, String> {
+    let response = ureq::get(url)
+        .timeout(Duration::from_secs(30))
+        .call()
+        .map_err(|e| e.to_string())?;
+    let ext = match response.content_type() {
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "image/avif" => "avif",
+        t if t.starts_with("image/") => "jpg",
+        t => return Err(format!("not an image: {}", t)),
+    };
+    let mut data = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut data)
+        .map_err(|e| e.to_string())?;
+    let hash: String = Sha256::digest(&data)[..16]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let file = format!("{}.{}", hash, ext);
+    let path = cache_dir().join(&file);
+    fs::create_dir_all(cache_dir()).map_err(|e| e.to_string())?;
+    fs::write(&path, data).map_err(|e| e.to_string())?;
+    Ok(file)
+}
+
+// drops the least recently used images until the cache fits its limit
+fn evict(index: &mut HashMap<String, String>) {
+    let mut files: Vec<(SystemTime, u64, String)> = match fs::read_dir(cache_dir()) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path() != index_path())
+            .filter_map(|e| {
+                let meta = e.metadata().ok()?;
+                let name = e.file_name().into_string().ok()?;
+                Some((meta.modified().ok()?, meta.len(), name))
+            })
+            .collect(),
+        Err(_) => return,
+    };
+    let limit = CONFIG.images.max_size * 1024 * 1024;
+    let mut total: u64 = files.iter().map(|f| f.1).sum();
+    files.sort();
+    for (_, size, name) in files {
+        if total <= limit {
+            break;
+        }
+        debug!("evicting {}", name);
+        if fs::remove_file(cache_dir().join(&name)).is_ok() {
+            total -= size;
+            index.retain(|_, f| *f != name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_srcs() {
+        assert_eq!(
+            file_src(Path::new("/home/me/my feeds/#1/a.png")).as_deref(),
+            Some("file:///home/me/my%20feeds/%231/a.png")
+        );
+        assert_eq!(
+            file_src(Path::new("/tmp/it's \"here\"&there.png")).as_deref(),
+            Some("file:///tmp/it&#39;s%20%22here%22&amp;there.png")
+        );
+        assert_eq!(file_src(Path::new("relative.png")), None);
+    }
+}
@@ -0,0 +1,136 @@
+use serde_json::{self, Value};
+
+use dates::parse_date;
+use types::{Enclosure, Feed, Item};
+
+// https://www.jsonfeed.org/version/1.1/, both versions read the same here
+#[derive(Deserialize)]
+struct JsonFeed {
+    #[serde(default)]
+    title: String,
+    home_page_url: Option<String>,
+    description: Option<String>,
+    #[serde(default)]
+    items: Vec<JsonItem>,
+}
+
+#[derive(Deserialize)]
+struct JsonItem {
+    #[serde(default)]
+    id: Value,
+    url: Option<String>,
+    external_url: Option<String>,
+    title: Option<String>,
+    content_html: Option<String>,
+    content_text: Option<String>,
+    summary: Option<String>,
+    date_published: Option<String>,
+    date_modified: Option<String>,
+    author: Option<Author>,
+    #[serde(default)]
+    authors: Vec<Author>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    attachments: Vec<Attachment>,
+}
+
+#[derive(Deserialize)]
+struct Attachment {
+    url: String,
+    mime_type: Option<String>,
+    // the spec allows any number for these, fractions included
+    size_in_bytes: Option<f64>,
+    duration_in_seconds: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct Author {
+    name: Option<String>,
+}
+
+pub fn parse(data: &[u8]) -> Result<(Feed, Vec<Item>), String> {
+    let json: JsonFeed = serde_json::from_slice(data).map_err(|e| e.to_string())?;
+    let feed = Feed {
+        title: json.title,
+        site_link: json.home_page_url,
+        description: json.description,
+        ..Default::default()
+    };
+    let items = json.items.into_iter().map(item).collect();
+    Ok((feed, items))
+}
+
+fn item(i: JsonItem) -> Item {
+    // ids may be numbers in older feeds
+    let guid = match i.id {
+        Value::String(s) => s,
+        Value::Null => String::new(),
+        other => other.to_string(),
+    };
+    let link = i.url.or(i.external_url).unwrap_or_default();
+    let updated_at = i.date_modified.as_deref().and_then(parse_date);
+    let mut item = Item {
+        title: i.title.unwrap_or_default(),
+        author: i.authors.into_iter().chain(i.author).find_map(|a| a.name),
+        summary: i.summary,
+        content: i.content_html.or(i.content_text),
+        published_at: i
+            .date_published
+            .as_deref()
+            .and_then(parse_date)
+            .or(updated_at),
+        updated_at,
+        categories: i.tags,
+        enclosures: i
+            .attachments
+            .into_iter()
+            .map(|a| Enclosure {
+                url: a.url,
+                mime_type: a.mime_type,
+                length: a.size_in_bytes.map(|s| s.round() as u64),
+                duration: a
+                    .duration_in_seconds
+                    .map(|d| (d.round() as u64).to_string()),
+            })
+            .collect(),
+        guid,
+        link,
+        ..Default::default()
+    };
+    if item.guid.is_empty() {
+        item.guid = item.link.clone();
+    }
+    item
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn items_without_ids() {
+        let json = br#"{"items": [
+            {"url": "https://example.com/a"},
+            {"id": 7, "url": "https://example.com/b"},
+            {"id": "c"}
+        ]}"#;
+        let (_, items) = parse(json).unwrap();
+        let guids: Vec<&str> = items.iter().map(|i| i.guid.as_str()).collect();
+        assert_eq!(guids, vec!["https://example.com/a", "7", "c"]);
+    }
+
+    #[test]
+    fn fractional_attachments() {
+        let json = br#"{"items": [{"id": "1", "attachments": [
+            {"url": "https://example.com/a.mp3", "size_in_bytes": 1024, "duration_in_seconds": 12.5},
+            {"url": "https://example.com/b.mp3", "size_in_bytes": 2e3, "duration_in_seconds": 60}
+        ]}]}"#;
+        let (_, items) = parse(json).unwrap();
+        let enclosures = &items[0].enclosures;
+        assert_eq!(enclosures[0].length, Some(1024));
+        assert_eq!(enclosures[0].duration.as_deref(), Some("13"));
+        assert_eq!(enclosures[1].length, Some(2000));
+        assert_eq!(enclosures[1].duration.as_deref(), Some("60"));
+    }
+}
@@ -0,0 +1,81 @@
+use chrono::{DateTime, Utc};
+use dirs;
+use std::fs;
+use std::path::{Path, PathBuf};
+use url::Url;
+
+use types::{Feed, Item};
+
+// files bigger than this are listed without their content
+const MAX_CONTENT: u64 = 1024 * 1024;
+
+// file:// URLs and anything without a scheme are local paths
+pub fn local_path(url: &str) -> Option<PathBuf> {
+    if url.starts_with("file://") {
+        return Url::parse(url).ok().and_then(|u| u.to_file_path().ok());
+    }
+    if url.contains("://") || url.starts_with("exec:") {
+        return None;
+    }
+    match url.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().map(|h| h.join(rest)),
+        None => Some(PathBuf::from(url)),
+    }
+}
+
+// every file in the directory is an item; its guid includes the modification
+// time so a changed file shows up again as a new item
+pub fn read_dir(dir: &Path) -> Result<(Feed, Vec<Item>), String> {
+    let entries = fs::read_dir(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let mut items = vec![];
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let meta = match entry.metadata() {
+            Ok(m) if m.is_file() && !name.starts_with('.') => m,
+            _ => continue,
+        };
+        let modified: Option<DateTime<Utc>> = meta.modified().ok().map(DateTime::from);
+        let content = match meta.len() <= MAX_CONTENT {
+            true => fs::read(&path).ok().and_then(|d| String::from_utf8(d).ok()),
+            false => None,
+        };
+        items.push(Item {
+            guid: format!(
+                "{}@{}",
+                path.display(),
+                modified.map(|m| m.timestamp()).unwrap_or(0)
+            ),
+            title: content.as_deref().and_then(heading).unwrap_or(name),
+            link: Url::from_file_path(&path)
+                .map(|u| u.to_string())
+                .unwrap_or_default(),
+            content,
+            published_at: modified,
+            updated_at: modified,
+            ..Default::default()
+        });
+    }
+    let feed = Feed {
+        title: dir
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        site_link: Url::from_directory_path(dir).ok().map(|u| u.to_string()),
+        ..Default::default()
+    };
+    Ok((feed, items))
+}
+
+// a markdown note's first line names it when it's a heading
+fn heading(content: &str) -> Option<String> {
+    let first = content.lines().find(|l| !l.trim().is_empty())?;
+    let title = first
+        .trim_start()
+        .strip_prefix('#')?
+        .trim_start_matches('#');
+    match title.trim() {
+        "" => None,
+        t => Some(t.to_string()),
+    }
+}
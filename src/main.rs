@@ -1,32 +1,320 @@
 // #![allow(unused)]
+extern crate ammonia;
 extern crate base64;
+extern crate chardetng;
+extern crate chrono;
+#[macro_use]
+extern crate clap;
 extern crate dirs;
 extern crate dotenv;
+extern crate encoding_rs;
 #[macro_use]
 extern crate lazy_static;
+extern crate libc;
+#[macro_use]
 extern crate log;
 extern crate pretty_env_logger;
+extern crate quick_xml;
 extern crate regex;
+extern crate scraper;
+extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
 extern crate serde_yaml;
+extern crate sha2;
+extern crate ureq;
+extern crate url;
+extern crate zbus;
 
+mod cleaner;
+mod commands;
+mod dates;
+mod encoding;
+mod fetch;
+mod folders;
+mod fsck;
+mod hooks;
+mod images;
+mod jsonfeed;
+mod local;
+mod notify;
+mod open;
+mod opml;
+mod parser;
+mod podcast;
+mod prune;
+mod query;
+mod readability;
+mod recover;
+mod rules;
+mod sanitize;
+mod score;
+mod scrape;
+mod store;
 mod types;
+mod update;
 
+use cleaner::UrlCleaner;
+use score::Scorer;
 use types::*;
 
 use std::env;
 use std::sync::Arc;
 
+use clap::{App, AppSettings, Arg, SubCommand};
 use dotenv::dotenv;
 
 lazy_static! {
     static ref CONFIG: Arc<Config> = Arc::new(Config::load_config());
+    static ref CLEANER: UrlCleaner = UrlCleaner::new(&CONFIG.url_cleaning);
+    static ref SCORER: Scorer = Scorer::new(&CONFIG.scoring);
 }
 
 fn main() {
     dotenv().ok();
     env::set_var("RUST_LOG", "mercury=info");
     pretty_env_logger::init();
+
+    let tag = || {
+        Arg::with_name("tag")
+            .long("tag")
+            .short("t")
+            .takes_value(true)
+            .help("Only include feeds and items with this tag")
+    };
+    let matches = App::new(env!("CARGO_PKG_NAME"))
+        .version(env!("CARGO_PKG_VERSION"))
+        .about(env!("CARGO_PKG_DESCRIPTION"))
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(
+            SubCommand::with_name("update")
+                .about("Fetch new items for every feed")
+                .arg(tag()),
+        )
+        .subcommand(
+            SubCommand::with_name("daemon")
+                .about("Keep updating in the background, notifying about new items")
+                .arg(
+                    Arg::with_name("interval")
+                        .long("interval")
+                        .short("i")
+                        .takes_value(true)
+                        .default_value("300")
+                        .help("Seconds to wait between updates"),
+                )
+                .arg(tag()),
+        )
+        .subcommand(
+            SubCommand::with_name("list")
+                .about("List feeds with unread counts")
+                .arg(Arg::with_name("folder").help("Only list the feeds in this folder"))
+                .arg(
+                    Arg::with_name("depth")
+                        .long("depth")
+                        .short("d")
+                        .takes_value(true)
+                        .help("Collapse folders nested deeper than this"),
+                )
+                .arg(tag()),
+        )
+        .subcommand(
+            SubCommand::with_name("show")
+                .about("Show items, newest first")
+                .arg(
+                    Arg::with_name("feed")
+                        .help("Only show items from this feed, folder or query feed, or 'starred'"),
+                )
+                .arg(
+                    Arg::with_name("all")
+                        .long("all")
+                        .short("a")
+                        .help("Include items already read"),
+                )
+                .arg(
+                    Arg::with_name("sort")
+                        .long("sort")
+                        .short("s")
+                        .takes_value(true)
+                        .possible_values(&["date", "score"])
+                        .default_value("date")
+                        .help("Order items by date or by score"),
+                )
+                .arg(
+                    Arg::with_name("query")
+                        .long("query")
+                        .short("q")
+                        .takes_value(true)
+                        .help("Only show items matching this filter expression"),
+                )
+                .arg(tag()),
+        )
+        .subcommand(
+            SubCommand::with_name("read")
+                .about("Print an item and mark it as read")
+                .arg(Arg::with_name("item").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("open")
+                .about("Open items in the browser or the program set for their type")
+                .arg(Arg::with_name("items").required(true).multiple(true))
+                .arg(
+                    Arg::with_name("media")
+                        .long("media")
+                        .short("m")
+                        .help("Open the media of the items instead of their links"),
+                )
+                .arg(
+                    Arg::with_name("queue")
+                        .long("queue")
+                        .short("q")
+                        .help("Hand all urls for the same program to it at once"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("media")
+                .about("Print the media urls of an item, for an external player")
+                .arg(Arg::with_name("item").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("star")
+                .about("Star an item")
+                .arg(Arg::with_name("item").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("unstar")
+                .about("Remove the star from an item")
+                .arg(Arg::with_name("item").required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("tag")
+                .about("Add tags to a feed or an item")
+                .arg(
+                    Arg::with_name("target")
+                        .required(true)
+                        .help("A feed name or item id"),
+                )
+                .arg(Arg::with_name("tags").required(true).multiple(true))
+                .arg(
+                    Arg::with_name("remove")
+                        .long("remove")
+                        .short("r")
+                        .help("Remove the tags instead"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("rules")
+                .about("Work with kill-file rules")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("test")
+                        .about("Fetch a feed and show which items the rules would match")
+                        .arg(Arg::with_name("feed").required(true)),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("podcast")
+                .about("Download podcast episodes")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("queue")
+                        .about("Queue the enclosures of an item for download")
+                        .arg(Arg::with_name("item").required(true)),
+                )
+                .subcommand(SubCommand::with_name("download").about("Download everything queued"))
+                .subcommand(SubCommand::with_name("list").about("Show the download queue"))
+                .subcommand(
+                    SubCommand::with_name("transcript")
+                        .about("Download and show an episode's transcript")
+                        .arg(Arg::with_name("item").required(true)),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("prune")
+                .about("Delete the items the retention settings have no room for")
+                .arg(
+                    Arg::with_name("dry-run")
+                        .long("dry-run")
+                        .short("n")
+                        .help("Only show what would be deleted"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("fsck")
+                .about("Check the stored feeds and items for damage")
+                .arg(
+                    Arg::with_name("repair")
+                        .long("repair")
+                        .short("r")
+                        .help("Fix what can be fixed, moving bad files to the quarantine"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("compact").about("Remove files the store no longer needs"),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Print the subscriptions as OPML")
+                .arg(tag()),
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .about("Print the feeds of an OPML file as config entries")
+                .arg(Arg::with_name("file").required(true)),
+        )
+        .get_matches();
+
+    match matches.subcommand() {
+        ("update", Some(m)) => update::update_all(m.value_of("tag")),
+        ("daemon", Some(m)) => update::daemon(
+            value_t!(m, "interval", u64).unwrap_or_else(|e| e.exit()),
+            m.value_of("tag"),
+        ),
+        ("list", Some(m)) => commands::list(
+            m.value_of("folder"),
+            match m.is_present("depth") {
+                true => Some(value_t!(m, "depth", usize).unwrap_or_else(|e| e.exit())),
+                false => None,
+            },
+            m.value_of("tag"),
+        ),
+        ("show", Some(m)) => commands::show(
+            m.value_of("feed"),
+            m.is_present("all"),
+            m.value_of("tag"),
+            m.value_of("query"),
+            m.value_of("sort") == Some("score"),
+        ),
+        ("read", Some(m)) => commands::read(m.value_of("item").unwrap()),
+        ("open", Some(m)) => open::open(
+            &m.values_of("items").unwrap().collect::<Vec<_>>(),
+            m.is_present("media"),
+            m.is_present("queue"),
+        ),
+        ("media", Some(m)) => commands::media(m.value_of("item").unwrap()),
+        ("star", Some(m)) => commands::star(m.value_of("item").unwrap(), true),
+        ("unstar", Some(m)) => commands::star(m.value_of("item").unwrap(), false),
+        ("tag", Some(m)) => commands::tag(
+            m.value_of("target").unwrap(),
+            m.values_of("tags").unwrap().collect(),
+            m.is_present("remove"),
+        ),
+        ("rules", Some(m)) => match m.subcommand() {
+            ("test", Some(m)) => commands::test_rules(m.value_of("feed").unwrap()),
+            _ => unreachable!(),
+        },
+        ("podcast", Some(m)) => match m.subcommand() {
+            ("queue", Some(m)) => podcast::queue(m.value_of("item").unwrap()),
+            ("download", Some(_)) => podcast::download(),
+            ("list", Some(_)) => podcast::list(),
+            ("transcript", Some(m)) => podcast::transcript(m.value_of("item").unwrap()),
+            _ => unreachable!(),
+        },
+        ("prune", Some(m)) => commands::prune(m.is_present("dry-run")),
+        ("fsck", Some(m)) => fsck::fsck(m.is_present("repair")),
+        ("compact", Some(_)) => fsck::compact(),
+        ("export", Some(m)) => opml::export(m.value_of("tag")),
+        ("import", Some(m)) => opml::import(m.value_of("file").unwrap()),
+        _ => unreachable!(),
+    }
 }
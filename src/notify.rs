@@ -0,0 +1,246 @@
+use std::collections::HashMap;
+use std::io::{self, IsTerminal, Write};
+use zbus::blocking::Connection;
+use zbus::zvariant::Value;
+
+use query::{inherited, Context, Inherited, Query};
+use types::Item;
+use CONFIG;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Method {
+    Auto,
+    Dbus,
+    Terminal,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Notify {
+    #[serde(default)]
+    pub queries: Vec<String>,
+    #[serde(default = "Notify::default_method")]
+    pub method: Method,
+}
+impl Notify {
+    fn default_method() -> Method {
+        Method::Auto
+    }
+}
+impl Default for Notify {
+    fn default() -> Self {
+        Notify {
+            queries: vec![],
+            method: Self::default_method(),
+        }
+    }
+}
+
+// how many titles a notification lists before summarising the rest
+const MAX_LINES: usize = 5;
+
+// sends a single notification for every new item matching one of the
+// configured queries, however many there are
+pub fn notify_new_items(items: &[Item]) {
+    let queries: Vec<(&str, Query)> = CONFIG
+        .notify
+        .queries
+        .iter()
+        .filter_map(|name| match CONFIG.find_query(name) {
+            Some(q) => match Query::parse(&q.query) {
+                Ok(query) => Some((name.as_str(), query)),
+                Err(e) => {
+                    warn!("invalid query for {}: {}", name, e);
+                    None
+                }
+            },
+            None => {
+                warn!("no such query to notify on: {}", name);
+                None
+            }
+        })
+        .collect();
+    if queries.is_empty() {
+        return;
+    }
+    let matched = matching(items, &queries, &inherited());
+    if let Some((summary, body)) = message(&matched) {
+        send(&summary, &body);
+    }
+}
+
+// each visible item with the name of the first query it matches
+fn matching<'a>(
+    items: &'a [Item],
+    queries: &[(&'a str, Query)],
+    feeds: &Inherited,
+) -> Vec<(&'a str, &'a Item)> {
+    items
+        .iter()
+        .filter(|i| !i.hidden)
+        .filter_map(|i| {
+            let ctx = Context::new(i, feeds);
+            queries
+                .iter()
+                .find(|(_, q)| q.matches(&ctx))
+                .map(|(name, _)| (*name, i))
+        })
+        .collect()
+}
+
+fn message(matched: &[(&str, &Item)]) -> Option<(String, String)> {
+    let summary = match matched.len() {
+        0 => return None,
+        1 => "1 new item".to_string(),
+        n => format!("{} new items", n),
+    };
+    let mut lines: Vec<String> = matched
+        .iter()
+        .take(MAX_LINES)
+        .map(|(name, i)| format!("{}: {}", name, i.title))
+        .collect();
+    if matched.len() > MAX_LINES {
+        lines.push(format!("and {} more", matched.len() - MAX_LINES));
+    }
+    Some((summary, lines.join("\n")))
+}
+
+fn send(summary: &str, body: &str) {
+    match CONFIG.notify.method {
+        Method::Terminal => terminal(summary, body),
+        Method::Dbus => {
+            if let Err(e) = dbus(summary, body) {
+                error!("could not send notification: {}", e);
+            }
+        }
+        Method::Auto => {
+            if let Err(e) = dbus(summary, body) {
+                debug!("no desktop notifications, using the terminal: {}", e);
+                terminal(summary, body);
+            }
+        }
+    }
+}
+
+// servers that support markup in the body would read a `<` or `&` in a
+// title as part of it
+fn dbus(summary: &str, body: &str) -> zbus::Result<()> {
+    let body = escape_markup(body);
+    let connection = Connection::session()?;
+    let hints: HashMap<&str, Value> = HashMap::new();
+    connection.call_method(
+        Some("org.freedesktop.Notifications"),
+        "/org/freedesktop/Notifications",
+        Some("org.freedesktop.Notifications"),
+        "Notify",
+        &(
+            env!("CARGO_PKG_NAME"),
+            0u32,
+            "",
+            summary,
+            body.as_str(),
+            Vec::<&str>::new(),
+            hints,
+            -1i32,
+        ),
+    )?;
+    Ok(())
+}
+
+fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// a bell plus an OSC 9 desktop notification, for terminals that support it
+fn terminal(summary: &str, body: &str) {
+    let text = terminal_text(summary, body);
+    let mut out = io::stdout();
+    if !out.is_terminal() {
+        info!("{}", text);
+        return;
+    }
+    let _ = write!(out, "\x07\x1b]9;{}\x1b\\", text);
+    let _ = out.flush();
+}
+
+// titles come from feeds, and an escape or bell among them would end the
+// sequence early and let the rest through to the terminal
+fn terminal_text(summary: &str, body: &str) -> String {
+    format!("{}: {}", summary, body.replace('\n', "; "))
+        .chars()
+        .filter(|c| !c.is_control())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(feed: &str, title: &str) -> Item {
+        Item {
+            feed: feed.to_string(),
+            title: title.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn first_matching_query_wins() {
+        let items = vec![
+            item("blog", "Rust 2.0"),
+            item("news", "Rust wins"),
+            Item {
+                hidden: true,
+                ..item("news", "Rust hidden")
+            },
+            item("news", "Weather"),
+        ];
+        let queries = vec![
+            ("rust", Query::parse(r#"title =~ "Rust""#).unwrap()),
+            ("news", Query::parse("feed = news").unwrap()),
+        ];
+        let matched: Vec<(&str, &str)> = matching(&items, &queries, &Inherited::new())
+            .into_iter()
+            .map(|(q, i)| (q, i.title.as_str()))
+            .collect();
+        assert_eq!(
+            matched,
+            vec![
+                ("rust", "Rust 2.0"),
+                ("rust", "Rust wins"),
+                ("news", "Weather")
+            ]
+        );
+    }
+
+    #[test]
+    fn one_message_for_all() {
+        assert_eq!(message(&[]), None);
+        let one = item("blog", "Hello");
+        assert_eq!(
+            message(&[("q", &one)]),
+            Some(("1 new item".to_string(), "q: Hello".to_string()))
+        );
+        let items: Vec<Item> = (1..=7)
+            .map(|n| item("blog", &format!("post {}", n)))
+            .collect();
+        let matched: Vec<(&str, &Item)> = items.iter().map(|i| ("q", i)).collect();
+        let (summary, body) = message(&matched).unwrap();
+        assert_eq!(summary, "7 new items");
+        assert_eq!(
+            body,
+            "q: post 1\nq: post 2\nq: post 3\nq: post 4\nq: post 5\nand 2 more"
+        );
+    }
+
+    #[test]
+    fn escaping() {
+        assert_eq!(escape_markup("Q&A: <b> vs >"), "Q&amp;A: &lt;b&gt; vs &gt;");
+        assert_eq!(
+            terminal_text("2 new items", "q: a\x1b]9;x\x07b\nq: c"),
+            "2 new items: q: a]9;xb; q: c"
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use std::env;
+use std::process::Command;
+use url::Url;
+
+use local::local_path;
+use store::{find_item, mark_item_as_read};
+use {CLEANER, CONFIG};
+
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct Open {
+    // falls back to $BROWSER, then xdg-open
+    #[serde(default)]
+    pub browser: Option<String>,
+    #[serde(default)]
+    pub handlers: Vec<Handler>,
+}
+
+// `{}` in the command stands for the urls, otherwise they go at the end
+#[derive(Deserialize, Debug, Clone)]
+pub struct Handler {
+    pub mime: String,
+    pub command: String,
+}
+
+// opens the items' links, or their media, each with the program configured
+// for its type; with `batch` every url for one program goes in one call
+pub fn open(ids: &[&str], media: bool, batch: bool) {
+    let mut targets: Vec<(String, String)> = vec![];
+    for id in ids {
+        let mut item = match find_item(id) {
+            Some(i) => i,
+            None => {
+                error!("no such item: {}", id);
+                continue;
+            }
+        };
+        // only feeds read from files may point at files
+        let local = CONFIG
+            .find_feed(&item.feed)
+            .is_some_and(|f| local_path(&f.url).is_some());
+        if media {
+            if item.enclosures.is_empty() {
+                warn!("{} has no media", id);
+            }
+            for enc in item.enclosures.iter().filter(|e| openable(&e.url, local)) {
+                let mime = enc
+                    .mime_type
+                    .clone()
+                    .unwrap_or_else(|| guess_mime(&enc.url).to_string());
+                targets.push((command_for(&mime), enc.url.clone()));
+            }
+        } else if item.link.is_empty() {
+            warn!("{} has no link", id);
+        } else {
+            let url = CLEANER.clean(&item.link);
+            if openable(&url, local) {
+                targets.push((command_for(guess_mime(&url)), url));
+            }
+        }
+        mark_item_as_read(&mut item);
+    }
+    if batch {
+        // keep the order in which each program first appears
+        let mut commands: Vec<&String> = vec![];
+        for (c, _) in &targets {
+            if !commands.contains(&c) {
+                commands.push(c);
+            }
+        }
+        for c in commands {
+            let urls: Vec<&str> = targets
+                .iter()
+                .filter(|(tc, _)| tc == c)
+                .map(|(_, u)| u.as_str())
+                .collect();
+            run(c, &urls);
+        }
+    } else {
+        for (c, url) in &targets {
+            run(c, &[url]);
+        }
+    }
+}
+
+// the urls come from feeds; anything but a web url, such as one starting
+// with `-`, could be taken for an option by the program, and a remote feed
+// has no business opening local files
+fn openable(url: &str, local: bool) -> bool {
+    let scheme = Url::parse(url).map(|u| u.scheme().to_string());
+    match scheme.as_deref() {
+        Ok("http") | Ok("https") => true,
+        Ok("file") if local => true,
+        Ok("file") => {
+            warn!("not opening {}: only local feeds may open files", url);
+            false
+        }
+        _ => {
+            warn!("not opening {}: only http(s) and file urls are opened", url);
+            false
+        }
+    }
+}
+
+fn command_for(mime: &str) -> String {
+    CONFIG
+        .open
+        .handlers
+        .iter()
+        .find(|h| mime_matches(&h.mime, mime))
+        .map(|h| h.command.clone())
+        .unwrap_or_else(browser)
+}
+
+fn browser() -> String {
+    CONFIG
+        .open
+        .browser
+        .clone()
+        // $BROWSER may list several, the first is enough
+        .or_else(|| {
+            env::var("BROWSER")
+                .ok()
+                .and_then(|b| b.split(':').next().map(|s| s.to_string()))
+                .filter(|b| !b.is_empty())
+        })
+        .unwrap_or_else(|| "xdg-open".to_string())
+}
+
+// `video/*` matches any video type
+fn mime_matches(pattern: &str, mime: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some(kind) => mime.split('/').next() == Some(kind),
+        None => pattern == "*" || pattern.eq_ignore_ascii_case(mime),
+    }
+}
+
+// links rarely come with a type, so go by the extension
+fn guess_mime(url: &str) -> &'static str {
+    let path = url.split(['?', '#']).next().unwrap_or("").to_lowercase();
+    let ext = match path.rsplit_once('.') {
+        Some((_, e)) if !e.contains('/') => e.to_string(),
+        _ => return "text/html",
+    };
+    match ext.as_str() {
+        "pdf" => "application/pdf",
+        "mp4" | "m4v" => "video/mp4",
+        "webm" => "video/webm",
+        "mkv" => "video/x-matroska",
+        "mov" => "video/quicktime",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "ogg" | "oga" | "opus" => "audio/ogg",
+        "flac" => "audio/flac",
+        "wav" => "audio/wav",
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        _ => "text/html",
+    }
+}
+
+// the urls are passed to the shell as arguments so they need no quoting
+fn run(command: &str, urls: &[&str]) {
+    let script = match command.contains("{}") {
+        true => command.replace("{}", "\"$@\""),
+        false => format!("{} \"$@\"", command),
+    };
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(&script)
+        .arg("sh")
+        .args(urls)
+        .status();
+    match status {
+        Ok(s) if s.success() => (),
+        Ok(s) => warn!("'{}' exited with {}", command, s),
+        Err(e) => error!("could not run '{}': {}", command, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn openable_urls() {
+        assert!(openable("https://example.com/a", false));
+        assert!(openable("http://example.com/a", true));
+        assert!(openable("file:///home/me/notes/a.md", true));
+        assert!(!openable("file:///etc/passwd", false));
+        assert!(!openable("--script=x.lua", true));
+        assert!(!openable("javascript:alert(1)", true));
+    }
+}
@@ -0,0 +1,163 @@
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde_yaml;
+use std::fs;
+use std::io::BufRead;
+
+use folders::Folder;
+use store::{feed_tags, load_feed};
+use types::FeedConfig;
+use CONFIG;
+
+pub fn export(tag: Option<&str>) {
+    let mut root = Folder::new();
+    for conf in &CONFIG.feeds {
+        let tags = feed_tags(conf);
+        if let Some(t) = tag {
+            if !tags.iter().any(|x| x == t) {
+                continue;
+            }
+        }
+        root.insert(&conf.folder_path(), (conf, tags));
+    }
+    println!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+    println!("<opml version=\"2.0\">");
+    println!(
+        "  <head><title>{} subscriptions</title></head>",
+        env!("CARGO_PKG_NAME")
+    );
+    println!("  <body>");
+    export_folder(&root, 2);
+    println!("  </body>");
+    println!("</opml>");
+}
+
+// folders become outlines without an xmlUrl, nesting the feeds inside them
+fn export_folder(folder: &Folder<(&FeedConfig, Vec<String>)>, level: usize) {
+    let indent = "  ".repeat(level);
+    for (name, child) in &folder.children {
+        println!("{}<outline text=\"{}\">", indent, attr(name));
+        export_folder(child, level + 1);
+        println!("{}</outline>", indent);
+    }
+    for (conf, tags) in &folder.feeds {
+        let feed = load_feed(&conf.name()).unwrap_or_default();
+        let title = if feed.title.is_empty() {
+            conf.name()
+        } else {
+            feed.title
+        };
+        let mut outline = format!(
+            "{}<outline type=\"rss\" text=\"{}\" title=\"{}\" xmlUrl=\"{}\"",
+            indent,
+            attr(&title),
+            attr(&title),
+            attr(&conf.url)
+        );
+        if let Some(link) = feed.site_link {
+            outline.push_str(&format!(" htmlUrl=\"{}\"", attr(&link)));
+        }
+        if !tags.is_empty() {
+            outline.push_str(&format!(" category=\"{}\"", attr(&tags.join(","))));
+        }
+        println!("{}/>", outline);
+    }
+}
+
+// prints the subscriptions as config entries, ready to paste under `feeds:`
+pub fn import(path: &str) {
+    let data = match fs::read(path) {
+        Ok(d) => d,
+        Err(e) => {
+            error!("could not read {}: {}", path, e);
+            return;
+        }
+    };
+    let mut reader = Reader::from_reader(&data[..]);
+    reader.trim_text(true);
+    let mut feeds = vec![];
+    let mut folders: Vec<Option<String>> = vec![];
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event(&mut buf) {
+            Ok(Event::Start(ref e)) if e.name() == b"outline" => {
+                let (text, feed) = outline(e, &reader, &folders);
+                match feed {
+                    Some(f) => {
+                        feeds.push(f);
+                        folders.push(None);
+                    }
+                    None => folders.push(text),
+                }
+            }
+            Ok(Event::Empty(ref e)) if e.name() == b"outline" => {
+                if let (_, Some(f)) = outline(e, &reader, &folders) {
+                    feeds.push(f);
+                }
+            }
+            Ok(Event::End(ref e)) if e.name() == b"outline" => {
+                folders.pop();
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => {
+                error!("could not parse {}: {}", path, e);
+                return;
+            }
+            _ => (),
+        }
+        buf.clear();
+    }
+    match serde_yaml::to_string(&feeds) {
+        Ok(s) => println!("{}", s),
+        Err(e) => error!("could not serialize feeds: {}", e),
+    }
+}
+
+fn outline<B: BufRead>(
+    e: &BytesStart,
+    reader: &Reader<B>,
+    folders: &[Option<String>],
+) -> (Option<String>, Option<FeedConfig>) {
+    let mut text = None;
+    let mut url = None;
+    let mut tags = vec![];
+    for a in e.attributes().filter_map(|a| a.ok()) {
+        let value = match a.unescape_and_decode_value(reader) {
+            Ok(v) => v,
+            Err(_) => continue,
+        };
+        match a.key {
+            b"text" => text = Some(value),
+            b"xmlUrl" => url = Some(value),
+            b"category" => tags = categories(&value),
+            _ => (),
+        }
+    }
+    let folder: Vec<&str> = folders
+        .iter()
+        .filter_map(|f| f.as_ref().map(|s| s.as_str()))
+        .collect();
+    let feed = url.map(|url| FeedConfig {
+        url,
+        tags,
+        folder: match folder.is_empty() {
+            true => None,
+            false => Some(folder.join("/")),
+        },
+        ..Default::default()
+    });
+    (text, feed)
+}
+
+fn categories(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|c| c.trim().trim_matches('/').to_string())
+        .filter(|c| !c.is_empty())
+        .collect()
+}
+
+fn attr(value: &str) -> String {
+    String::from_utf8_lossy(&escape(value.as_bytes())).into_owned()
+}
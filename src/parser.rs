@@ -0,0 +1,358 @@
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use dates::parse_date;
+use jsonfeed;
+use recover::repair;
+use types::{Enclosure, Episode, Feed, Item, Transcript};
+
+pub fn parse(data: &[u8]) -> Result<(Feed, Vec<Item>), String> {
+    if data.iter().find(|b| !b.is_ascii_whitespace()) == Some(&b'{') {
+        return jsonfeed::parse(data);
+    }
+    parse_xml(data, false).or_else(|e| {
+        debug!("trying to recover from broken XML: {}", e);
+        let (mut feed, items) = parse_xml(&repair(data), true).map_err(|_| e.clone())?;
+        feed.warning = Some(format!("recovered from broken XML ({})", e));
+        Ok((feed, items))
+    })
+}
+
+// in salvage mode, text that still won't unescape is kept as it is and the
+// first syntax error ends the document, keeping the items read until then
+fn parse_xml(data: &[u8], salvage: bool) -> Result<(Feed, Vec<Item>), String> {
+    let mut reader = Reader::from_reader(data);
+    reader.trim_text(true).check_end_names(false);
+
+    let mut feed = Feed::default();
+    let mut items = vec![];
+    let mut current: Option<Item> = None;
+    let mut orig_link: Option<String> = None;
+    let mut stack: Vec<String> = vec![];
+    let mut text = String::new();
+    // atom's xhtml content so far, and how many of its elements are open
+    let mut xhtml: Option<(String, usize)> = None;
+    let mut buf = Vec::new();
+    loop {
+        let event = reader.read_event(&mut buf);
+        if let (Some((markup, open)), Ok(e)) = (xhtml.as_mut(), event.as_ref()) {
+            if xhtml_event(e, markup, open) {
+                buf.clear();
+                continue;
+            }
+            // the end of the element holding it
+            text = xhtml.take().unwrap().0;
+            reader.trim_text(true);
+        }
+        match event {
+            Ok(Event::Start(ref e)) => {
+                let name = decode(e.name());
+                if name == "item" || name == "entry" {
+                    current = Some(Item::default());
+                    orig_link = None;
+                }
+                attributes(e, &name, &mut feed, &mut current);
+                stack.push(name);
+                text.clear();
+                if attribute(e, b"type").as_deref() == Some("xhtml") {
+                    xhtml = Some((String::new(), 0));
+                    reader.trim_text(false);
+                }
+            }
+            Ok(Event::Empty(ref e)) => attributes(e, &decode(e.name()), &mut feed, &mut current),
+            Ok(Event::Text(ref e)) => match e.unescape_and_decode(&reader) {
+                Ok(t) => text.push_str(&t),
+                Err(_) if salvage => text.push_str(&String::from_utf8_lossy(e.escaped())),
+                Err(e) => return Err(error(&reader, e)),
+            },
+            Ok(Event::CData(ref e)) => {
+                // quick-xml hands CDATA over escaped
+                let raw = e.unescaped().map_err(|e| error(&reader, e))?;
+                text.push_str(&String::from_utf8_lossy(&raw));
+            }
+            Ok(Event::End(_)) => {
+                let name = match stack.pop() {
+                    Some(n) => n,
+                    None => continue,
+                };
+                let parent = stack.last().map(|s| s.as_str()).unwrap_or("");
+                let value = text.trim().to_string();
+                text.clear();
+                match current {
+                    Some(_) if name == "item" || name == "entry" => {
+                        let mut item = current.take().unwrap();
+                        if let Some(l) = orig_link.take() {
+                            item.link = l;
+                        }
+                        // itunes gives the duration per item, not per file
+                        if let Some(d) = item.episode.as_ref().and_then(|e| e.duration.clone()) {
+                            for enc in &mut item.enclosures {
+                                enc.duration.get_or_insert_with(|| d.clone());
+                            }
+                        }
+                        if item.thumbnail.is_none() {
+                            item.thumbnail = item
+                                .enclosures
+                                .iter()
+                                .find(|e| {
+                                    e.mime_type
+                                        .as_deref()
+                                        .is_some_and(|t| t.starts_with("image/"))
+                                })
+                                .map(|e| e.url.clone())
+                                .or_else(|| item.episode.as_ref().and_then(|e| e.image.clone()));
+                        }
+                        if item.published_at.is_none() {
+                            item.published_at = item.updated_at;
+                        }
+                        if item.guid.is_empty() {
+                            item.guid = if item.link.is_empty() {
+                                item.title.clone()
+                            } else {
+                                item.link.clone()
+                            };
+                        }
+                        items.push(item);
+                    }
+                    // media:group nests these, media:content may too
+                    Some(ref mut item) if name == "media:description" && !value.is_empty() => {
+                        item.summary.get_or_insert(value);
+                    }
+                    Some(ref mut item) if name == "media:title" && item.title.is_empty() => {
+                        item.title = value
+                    }
+                    Some(ref mut item) if parent == "item" || parent == "entry" => {
+                        if name == "feedburner:origLink" {
+                            orig_link = Some(value);
+                        } else {
+                            item_field(item, &name, value);
+                        }
+                    }
+                    Some(ref mut item) if parent == "author" && name == "name" => {
+                        item.author = Some(value)
+                    }
+                    None if parent == "channel" || parent == "feed" => {
+                        feed_field(&mut feed, &name, value)
+                    }
+                    _ => (),
+                }
+            }
+            Ok(Event::Eof) if !stack.is_empty() && !salvage => {
+                return Err(format!("document ends inside <{}>", stack.join("><")));
+            }
+            Ok(Event::Eof) => break,
+            Err(e) if salvage => {
+                debug!("giving up on the rest: {}", error(&reader, e));
+                break;
+            }
+            Err(e) => return Err(error(&reader, e)),
+            _ => (),
+        }
+        buf.clear();
+    }
+    if feed.title.is_empty() && items.is_empty() {
+        return Err("no feed found in document".to_string());
+    }
+    Ok((feed, items))
+}
+
+// copies the markup inside an xhtml element as it is, telling whether the
+// event was part of it rather than the element's end
+fn xhtml_event(event: &Event, markup: &mut String, open: &mut usize) -> bool {
+    match event {
+        Event::Start(e) => {
+            *open += 1;
+            markup.push_str(&format!("<{}>", decode(e)));
+        }
+        Event::Empty(e) => markup.push_str(&format!("<{}/>", decode(e))),
+        Event::End(e) if *open > 0 => {
+            *open -= 1;
+            markup.push_str(&format!("</{}>", decode(e.name())));
+        }
+        Event::End(_) | Event::Eof => return false,
+        Event::Text(e) => markup.push_str(&decode(e.escaped())),
+        Event::CData(e) => {
+            let raw = decode(e.escaped());
+            markup.push_str(&raw.replace('&', "&amp;").replace('<', "&lt;"));
+        }
+        _ => (),
+    }
+    true
+}
+
+fn item_field(item: &mut Item, name: &str, value: String) {
+    if value.is_empty() {
+        return;
+    }
+    match name {
+        "title" => item.title = value,
+        "link" => item.link = value,
+        "guid" | "id" => item.guid = value,
+        "description" | "summary" => item.summary = Some(value),
+        "content:encoded" | "content" => item.content = Some(value),
+        "author" | "dc:creator" => item.author = Some(value),
+        "category" | "dc:subject" => item.categories.push(value),
+        "pubDate" | "published" | "dc:date" | "issued" if item.published_at.is_none() => {
+            item.published_at = parse_date(&value)
+        }
+        "updated" | "modified" => item.updated_at = parse_date(&value),
+        "itunes:duration" => episode(item).duration = Some(value),
+        "itunes:episode" | "podcast:episode" => episode(item).episode = value.parse().ok(),
+        "itunes:season" | "podcast:season" => episode(item).season = value.parse().ok(),
+        _ => (),
+    }
+}
+
+fn episode(item: &mut Item) -> &mut Episode {
+    item.episode.get_or_insert_with(Episode::default)
+}
+
+fn feed_field(feed: &mut Feed, name: &str, value: String) {
+    if value.is_empty() {
+        return;
+    }
+    match name {
+        "title" => feed.title = value,
+        "link" => feed.site_link = Some(value),
+        "description" | "subtitle" => feed.description = Some(value),
+        _ => (),
+    }
+}
+
+// atom links carry their target in attributes rather than text
+fn link_attributes(e: &BytesStart, feed: &mut Feed, current: &mut Option<Item>) {
+    let href = match attribute(e, b"href") {
+        Some(h) => h,
+        None => return,
+    };
+    let rel = attribute(e, b"rel").unwrap_or_else(|| "alternate".to_string());
+    match current {
+        Some(item) if rel == "enclosure" => item.enclosures.push(Enclosure {
+            url: href,
+            mime_type: attribute(e, b"type"),
+            length: attribute(e, b"length").and_then(|l| l.parse().ok()),
+            duration: None,
+        }),
+        _ if rel != "alternate" => (),
+        Some(item) => item.link = href,
+        None => feed.site_link = Some(href),
+    }
+}
+
+// elements that carry their value in attributes rather than text
+fn attributes(e: &BytesStart, name: &str, feed: &mut Feed, current: &mut Option<Item>) {
+    if name == "link" {
+        return link_attributes(e, feed, current);
+    }
+    let item = match current.as_mut() {
+        Some(i) => i,
+        None => return,
+    };
+    match name {
+        "category" => item.categories.extend(attribute(e, b"term")),
+        "enclosure" => {
+            if let Some(url) = attribute(e, b"url") {
+                item.enclosures.push(Enclosure {
+                    url,
+                    mime_type: attribute(e, b"type"),
+                    length: attribute(e, b"length").and_then(|l| l.parse().ok()),
+                    duration: None,
+                });
+            }
+        }
+        "media:content" => {
+            let url = match attribute(e, b"url") {
+                Some(u) => u,
+                None => return,
+            };
+            if item.enclosures.iter().any(|enc| enc.url == url) {
+                return;
+            }
+            // medium is all some feeds give instead of a type
+            let mime_type = attribute(e, b"type")
+                .or_else(|| attribute(e, b"medium").map(|m| format!("{}/*", m)));
+            item.enclosures.push(Enclosure {
+                url,
+                mime_type,
+                length: attribute(e, b"fileSize").and_then(|l| l.parse().ok()),
+                duration: attribute(e, b"duration"),
+            });
+        }
+        "media:thumbnail" if item.thumbnail.is_none() => item.thumbnail = attribute(e, b"url"),
+        "itunes:image" => episode(item).image = attribute(e, b"href"),
+        "podcast:chapters" => episode(item).chapters = attribute(e, b"url"),
+        "podcast:transcript" => {
+            if let Some(url) = attribute(e, b"url") {
+                episode(item).transcripts.push(Transcript {
+                    url,
+                    mime_type: attribute(e, b"type"),
+                    language: attribute(e, b"language"),
+                });
+            }
+        }
+        _ => (),
+    }
+}
+
+fn attribute(e: &BytesStart, key: &[u8]) -> Option<String> {
+    e.attributes()
+        .filter_map(|a| a.ok())
+        .find(|a| a.key == key)
+        .and_then(|a| a.unescaped_value().ok().map(|v| decode(&v)))
+}
+
+fn decode(name: &[u8]) -> String {
+    String::from_utf8_lossy(name).into_owned()
+}
+
+fn error<B: ::std::io::BufRead>(reader: &Reader<B>, e: ::quick_xml::Error) -> String {
+    format!("error at position {}: {}", reader.buffer_position(), e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rss() {
+        let (feed, items) = parse(
+            br#"<rss><channel><title>Blog</title><link>https://example.com/</link>
+            <item><title>One</title><link>https://example.com/1</link>
+            <description><![CDATA[<p>a & b</p>]]></description>
+            <pubDate>Tue, 10 Jun 2003 04:00:00 GMT</pubDate></item>
+            </channel></rss>"#,
+        )
+        .unwrap();
+        assert_eq!(feed.title, "Blog");
+        assert_eq!(feed.site_link.as_deref(), Some("https://example.com/"));
+        assert_eq!(items[0].title, "One");
+        assert_eq!(items[0].guid, "https://example.com/1");
+        assert_eq!(items[0].summary.as_deref(), Some("<p>a & b</p>"));
+        assert!(items[0].published_at.is_some());
+    }
+
+    #[test]
+    fn atom_content() {
+        let (_, items) = parse(
+            br#"<feed xmlns="http://www.w3.org/2005/Atom"><title>Atom</title>
+            <entry><id>1</id><title>Html</title>
+            <content type="html">&lt;p&gt;Hello &amp;amp; bye&lt;/p&gt;</content></entry>
+            <entry><id>2</id><title>Xhtml</title>
+            <summary>Short</summary>
+            <content type="xhtml"><div xmlns="http://www.w3.org/1999/xhtml"><p>Hello <b>big</b> <i>world</i> &amp; co<br/></p></div></content>
+            <link href="https://example.com/2"/></entry>
+            </feed>"#,
+        )
+        .unwrap();
+        assert_eq!(items[0].content.as_deref(), Some("<p>Hello &amp; bye</p>"));
+        assert_eq!(
+            items[1].content.as_deref(),
+            Some(
+                r#"<div xmlns="http://www.w3.org/1999/xhtml"><p>Hello <b>big</b> <i>world</i> &amp; co<br/></p></div>"#
+            )
+        );
+        // the elements after it are read as usual
+        assert_eq!(items[1].summary.as_deref(), Some("Short"));
+        assert_eq!(items[1].link, "https://example.com/2");
+    }
+}
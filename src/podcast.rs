@@ -0,0 +1,343 @@
+use dirs;
+use regex::Regex;
+use scraper::Html;
+use serde_json::{self, Value};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use ureq::{self, Agent, AgentBuilder};
+
+use commands::printable;
+use encoding::to_utf8;
+use fetch;
+use store::{find_item, load_queue, save_item, save_queue};
+use CONFIG;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Podcasts {
+    #[serde(default = "Podcasts::default_directory")]
+    pub directory: PathBuf,
+    // how many files to download at once
+    #[serde(default = "Podcasts::default_parallel")]
+    pub parallel: usize,
+}
+impl Podcasts {
+    fn default_directory() -> PathBuf {
+        dirs::data_dir()
+            .unwrap()
+            .join(env!("CARGO_PKG_NAME"))
+            .join("podcasts")
+    }
+
+    fn default_parallel() -> usize {
+        2
+    }
+}
+impl Default for Podcasts {
+    fn default() -> Self {
+        Podcasts {
+            directory: Self::default_directory(),
+            parallel: Self::default_parallel(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Download {
+    pub item: String,
+    pub title: String,
+    pub url: String,
+    pub path: PathBuf,
+    pub length: Option<u64>,
+    #[serde(default)]
+    pub done: bool,
+}
+
+pub fn queue(id: &str) {
+    let item = match find_item(id) {
+        Some(i) => i,
+        None => {
+            error!("no such item: {}", id);
+            return;
+        }
+    };
+    if item.enclosures.is_empty() {
+        error!("{} has no enclosures", id);
+        return;
+    }
+    let mut queue = load_queue();
+    for enc in &item.enclosures {
+        if queue.iter().any(|d| d.url == enc.url) {
+            info!("already queued: {}", enc.url);
+            continue;
+        }
+        // episodes are often all called episode.mp3, so prefix the item
+        let path = CONFIG.podcasts.directory.join(&item.feed).join(format!(
+            "{}-{}",
+            item.id,
+            file_name(&enc.url)
+        ));
+        info!("queued {}", path.display());
+        queue.push(Download {
+            item: item.id.clone(),
+            title: item.title.clone(),
+            url: enc.url.clone(),
+            path,
+            length: enc.length,
+            done: false,
+        });
+    }
+    save_queue(&queue);
+}
+
+pub fn list() {
+    for d in load_queue() {
+        let status = match d.done {
+            true => "done".to_string(),
+            false => match fs::metadata(part_path(&d.path)) {
+                Ok(m) => match d.length {
+                    Some(l) => format!("{}/{}", size(m.len()), size(l)),
+                    None => size(m.len()),
+                },
+                Err(_) => "queued".to_string(),
+            },
+        };
+        println!("{}  {:>17}  {}", d.item, status, printable(&d.title));
+    }
+}
+
+// downloads every pending file, a few at a time
+pub fn download() {
+    let mut queue = load_queue();
+    let pending: VecDeque<(usize, Download)> = queue
+        .iter()
+        .cloned()
+        .enumerate()
+        .filter(|(_, d)| !d.done)
+        .collect();
+    if pending.is_empty() {
+        info!("nothing to download");
+        return;
+    }
+    let workers = CONFIG.podcasts.parallel.clamp(1, pending.len());
+    let pending = Arc::new(Mutex::new(pending));
+    let finished = Arc::new(Mutex::new(vec![]));
+    let agent = AgentBuilder::new()
+        .timeout_connect(Duration::from_secs(30))
+        .timeout_read(Duration::from_secs(60))
+        .build();
+    let handles: Vec<_> = (0..workers)
+        .map(|_| {
+            let pending = pending.clone();
+            let finished = finished.clone();
+            let agent = agent.clone();
+            thread::spawn(move || loop {
+                let next = pending.lock().unwrap().pop_front();
+                let (i, d) = match next {
+                    Some(n) => n,
+                    None => break,
+                };
+                info!("downloading {}", d.url);
+                match fetch(&agent, &d) {
+                    Ok(()) => {
+                        info!("saved {}", d.path.display());
+                        finished.lock().unwrap().push(i);
+                    }
+                    Err(e) => error!("could not download {}: {}", d.url, e),
+                }
+            })
+        })
+        .collect();
+    for h in handles {
+        let _ = h.join();
+    }
+    for i in finished.lock().unwrap().iter() {
+        queue[*i].done = true;
+    }
+    save_queue(&queue);
+}
+
+// continues from a partial download when the server allows ranges
+fn fetch(agent: &Agent, d: &Download) -> Result<(), String> {
+    let part = part_path(&d.path);
+    if let Some(dir) = part.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let have = fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+    let mut request = agent.get(&d.url);
+    if have > 0 {
+        request = request.set("Range", &format!("bytes={}-", have));
+    }
+    let response = match request.call() {
+        Ok(r) => r,
+        // nothing left to fetch
+        Err(ureq::Error::Status(416, _)) if have > 0 => {
+            return fs::rename(&part, &d.path).map_err(|e| e.to_string());
+        }
+        Err(e) => return Err(e.to_string()),
+    };
+    let mut file = match response.status() {
+        206 => OpenOptions::new().append(true).open(&part),
+        _ => File::create(&part),
+    }
+    .map_err(|e| e.to_string())?;
+    io::copy(&mut response.into_reader(), &mut file).map_err(|e| e.to_string())?;
+    fs::rename(&part, &d.path).map_err(|e| e.to_string())
+}
+
+fn part_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+// the last segment of the url's path, made safe to use as a file name
+fn file_name(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or("");
+    let name: String = path
+        .rsplit('/')
+        .next()
+        .unwrap_or("")
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+    match name.trim_matches('.') {
+        "" => "download".to_string(),
+        n => n.to_string(),
+    }
+}
+
+// fetches the item's transcript once, keeping it as text with the item
+pub fn transcript(id: &str) {
+    let mut item = match find_item(id) {
+        Some(i) => i,
+        None => {
+            error!("no such item: {}", id);
+            return;
+        }
+    };
+    let episode = match item.episode.as_mut() {
+        Some(e) if e.transcript.is_some() || !e.transcripts.is_empty() => e,
+        _ => {
+            error!("{} has no transcript", id);
+            return;
+        }
+    };
+    if episode.transcript.is_none() {
+        let best = episode
+            .transcripts
+            .iter()
+            .min_by_key(|t| transcript_rank(t.mime_type.as_deref()))
+            .unwrap();
+        let data = match fetch::fetch(&best.url, Duration::from_secs(30)) {
+            Ok((body, content_type)) => to_utf8(body, content_type.as_deref()),
+            Err(e) => {
+                error!("could not fetch {}: {}", best.url, e);
+                return;
+            }
+        };
+        let text = String::from_utf8_lossy(&data);
+        episode.transcript = Some(transcript_text(&text, best.mime_type.as_deref()));
+        save_item(&item);
+    }
+    if let Some(text) = item.episode.and_then(|e| e.transcript) {
+        println!("{}", printable(&text));
+    }
+}
+
+// plain text needs the least cleaning up
+fn transcript_rank(mime_type: Option<&str>) -> usize {
+    match mime_type {
+        Some("text/plain") => 0,
+        Some("text/html") => 1,
+        Some("text/vtt") => 2,
+        Some("application/srt") | Some("application/x-subrip") => 3,
+        Some("application/json") => 4,
+        _ => 5,
+    }
+}
+
+fn transcript_text(data: &str, mime_type: Option<&str>) -> String {
+    match mime_type {
+        Some("text/html") => Html::parse_fragment(data)
+            .root_element()
+            .text()
+            .collect::<String>()
+            .trim()
+            .to_string(),
+        Some("text/vtt") | Some("application/srt") | Some("application/x-subrip") => cues(data),
+        Some("application/json") => segments(data).unwrap_or_else(|| data.to_string()),
+        _ => data.trim().to_string(),
+    }
+}
+
+// the spoken lines of a WebVTT or SubRip file, without numbers and timings
+fn cues(data: &str) -> String {
+    lazy_static! {
+        static ref VOICE: Regex = Regex::new(r"<v(?:\.[^ >]*)? ([^>]+)>").unwrap();
+        static ref TAG: Regex = Regex::new(r"<[^>]*>").unwrap();
+    }
+    let mut lines: Vec<String> = vec![];
+    let mut in_note = false;
+    for line in data.lines().map(|l| l.trim()) {
+        if line.is_empty() {
+            in_note = false;
+            continue;
+        }
+        if line.starts_with("NOTE") {
+            in_note = true;
+        }
+        if in_note
+            || line.starts_with("WEBVTT")
+            || line.contains("-->")
+            || line.chars().all(|c| c.is_ascii_digit())
+        {
+            continue;
+        }
+        let text = TAG
+            .replace_all(&VOICE.replace_all(line, "$1: "), "")
+            .into_owned();
+        // cues often repeat the tail of the one before
+        if lines.last() != Some(&text) {
+            lines.push(text);
+        }
+    }
+    lines.join("\n")
+}
+
+// the podcast namespace's JSON format, one paragraph per change of speaker
+fn segments(data: &str) -> Option<String> {
+    let json: Value = serde_json::from_str(data).ok()?;
+    let mut out = String::new();
+    let mut speaker: Option<&str> = None;
+    for seg in json.get("segments")?.as_array()? {
+        let body = seg.get("body").and_then(|b| b.as_str()).unwrap_or("");
+        let who = seg.get("speaker").and_then(|s| s.as_str());
+        match who {
+            Some(w) if who != speaker => {
+                out.push_str(&format!("\n\n{}: ", w));
+                speaker = who;
+            }
+            _ if !out.is_empty() => out.push(' '),
+            _ => (),
+        }
+        out.push_str(body.trim());
+    }
+    Some(out.trim().to_string())
+}
+
+pub fn size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 30 => format!("{:.1} GB", b as f64 / (1u64 << 30) as f64),
+        b if b >= 1 << 20 => format!("{:.1} MB", b as f64 / (1u64 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.1} kB", b as f64 / (1u64 << 10) as f64),
+        b => format!("{} B", b),
+    }
+}
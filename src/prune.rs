@@ -0,0 +1,168 @@
+use chrono::{Duration, Utc};
+use std::cmp::Reverse;
+
+use store::{delete_item, feed_names, load_feed, load_items, save_feed};
+use types::Item;
+use CONFIG;
+
+// set globally and per feed, where each setting overrides the global one
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Retention {
+    // how many of the newest items to keep
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_items: Option<usize>,
+    // how old items may get
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_days: Option<i64>,
+    // unread items are kept whatever their age unless this is false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_unread: Option<bool>,
+}
+impl Retention {
+    fn or(&self, other: &Retention) -> Retention {
+        Retention {
+            keep_items: self.keep_items.or(other.keep_items),
+            keep_days: self.keep_days.or(other.keep_days),
+            keep_unread: self.keep_unread.or(other.keep_unread),
+        }
+    }
+
+    fn is_unlimited(&self) -> bool {
+        self.keep_items.is_none() && self.keep_days.is_none()
+    }
+}
+
+fn policy(feed: &str) -> Retention {
+    match CONFIG.find_feed(feed).and_then(|f| f.retention.as_ref()) {
+        Some(r) => r.or(&CONFIG.retention),
+        None => CONFIG.retention.clone(),
+    }
+}
+
+// the items of a feed its retention policy has no room for
+pub fn expired_items(feed: &str) -> Vec<Item> {
+    expired(load_items(feed), &policy(feed))
+}
+
+// starred items are always kept, unread ones unless the policy says
+// otherwise; items hidden by rules are never shown, so never read either,
+// and go whatever the policy
+fn expired(mut items: Vec<Item>, policy: &Retention) -> Vec<Item> {
+    if policy.is_unlimited() {
+        return vec![];
+    }
+    items.sort_by_key(|i| Reverse(i.published_at));
+    // more days than chrono can count is the same as no limit
+    let cutoff = policy
+        .keep_days
+        .and_then(Duration::try_days)
+        .and_then(|d| Utc::now().checked_sub_signed(d));
+    let keep_unread = policy.keep_unread.unwrap_or(true);
+    items
+        .into_iter()
+        .enumerate()
+        .filter(|(i, item)| {
+            let too_many = policy.keep_items.is_some_and(|n| *i >= n);
+            let too_old = match (cutoff, item.published_at) {
+                (Some(c), Some(p)) => p < c,
+                _ => false,
+            };
+            (too_many || too_old) && !item.starred && (item.seen || item.hidden || !keep_unread)
+        })
+        .map(|(_, item)| item)
+        .collect()
+}
+
+// deletes the items, remembering them so the next update won't bring back
+// those still in the feed
+pub fn remove_items(feed: &str, items: &[Item]) {
+    if items.is_empty() {
+        return;
+    }
+    if let Some(mut f) = load_feed(feed) {
+        f.pruned.extend(items.iter().map(|i| i.id.clone()));
+        save_feed(&f);
+    }
+    items.iter().for_each(delete_item);
+}
+
+// run after updates, so it stays quiet unless it deleted something
+pub fn prune_all() {
+    for feed in feed_names() {
+        let expired = expired_items(&feed);
+        if !expired.is_empty() {
+            info!("pruned {} items from {}", expired.len(), feed);
+            remove_items(&feed, &expired);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str, days_old: i64, seen: bool) -> Item {
+        Item {
+            id: id.to_string(),
+            seen,
+            published_at: Some(Utc::now() - Duration::days(days_old)),
+            ..Default::default()
+        }
+    }
+
+    fn ids(items: Vec<Item>, policy: Retention) -> Vec<String> {
+        let mut ids: Vec<String> = expired(items, &policy).into_iter().map(|i| i.id).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn keep_items() {
+        let items = vec![item("c", 3, true), item("a", 1, true), item("b", 2, true)];
+        let policy = Retention {
+            keep_items: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(ids(items.clone(), policy), vec!["c"]);
+        assert!(ids(items, Retention::default()).is_empty());
+    }
+
+    #[test]
+    fn keep_days() {
+        let items = vec![item("a", 1, true), item("b", 10, true)];
+        let policy = |d| Retention {
+            keep_days: Some(d),
+            ..Default::default()
+        };
+        assert_eq!(ids(items.clone(), policy(5)), vec!["b"]);
+        assert!(ids(items, policy(99_999_999_999_999)).is_empty());
+    }
+
+    #[test]
+    fn starred_unread_and_hidden() {
+        let starred = Item {
+            starred: true,
+            ..item("starred", 10, true)
+        };
+        let hidden = Item {
+            hidden: true,
+            ..item("hidden", 10, false)
+        };
+        let items = vec![
+            item("read", 10, true),
+            item("unread", 10, false),
+            starred,
+            hidden,
+        ];
+        let policy = |keep_unread| Retention {
+            keep_days: Some(5),
+            keep_unread,
+            ..Default::default()
+        };
+        assert_eq!(ids(items.clone(), policy(None)), vec!["hidden", "read"]);
+        assert_eq!(
+            ids(items, policy(Some(false))),
+            vec!["hidden", "read", "unread"]
+        );
+    }
+}
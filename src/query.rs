@@ -0,0 +1,451 @@
+use chrono::{Duration, Utc};
+use regex::Regex;
+use std::collections::HashMap;
+
+use store::feed_tags;
+use types::Item;
+use CONFIG;
+
+// what a query is evaluated against: the item plus what it inherits
+// from its feed
+pub struct Context<'a> {
+    pub item: &'a Item,
+    pub tags: Vec<&'a str>,
+    pub folder: &'a str,
+}
+impl<'a> Context<'a> {
+    pub fn new(item: &'a Item, feeds: &'a Inherited) -> Self {
+        let mut tags: Vec<&str> = item.tags.iter().map(|t| t.as_str()).collect();
+        let mut folder = "";
+        if let Some((feed_tags, feed_folder)) = feeds.get(&item.feed) {
+            tags.extend(feed_tags.iter().map(|t| t.as_str()));
+            folder = feed_folder;
+        }
+        Context { item, tags, folder }
+    }
+}
+
+// the tags and folder of every feed, keyed by feed name
+pub type Inherited = HashMap<String, (Vec<String>, String)>;
+
+pub fn inherited() -> Inherited {
+    CONFIG
+        .feeds
+        .iter()
+        .map(|f| (f.name(), (feed_tags(f), f.folder_path().join("/"))))
+        .collect()
+}
+
+#[derive(Debug)]
+pub enum Query {
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+    Flag(Flag),
+    Compare(Field, Op, Value),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Flag {
+    Unread,
+    Read,
+    Starred,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Field {
+    Title,
+    Link,
+    Author,
+    Summary,
+    Content,
+    Feed,
+    Folder,
+    Tag,
+    Age,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Eq,
+    Ne,
+    Match,
+    NoMatch,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug)]
+pub enum Value {
+    Text(String),
+    Pattern(Regex),
+    Age(Duration),
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Query, String> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let query = parser.or()?;
+        match parser.peek() {
+            None => Ok(query),
+            Some(t) => Err(format!("unexpected '{}'", t.text())),
+        }
+    }
+
+    pub fn matches(&self, ctx: &Context) -> bool {
+        match self {
+            Query::And(a, b) => a.matches(ctx) && b.matches(ctx),
+            Query::Or(a, b) => a.matches(ctx) || b.matches(ctx),
+            Query::Not(q) => !q.matches(ctx),
+            Query::Flag(Flag::Unread) => !ctx.item.seen,
+            Query::Flag(Flag::Read) => ctx.item.seen,
+            Query::Flag(Flag::Starred) => ctx.item.starred,
+            Query::Compare(Field::Age, op, Value::Age(d)) => match ctx.item.published_at {
+                Some(date) => compare(Utc::now().signed_duration_since(date), *op, *d),
+                None => false,
+            },
+            Query::Compare(Field::Tag, op, value) => {
+                let found = ctx.tags.iter().any(|t| text_matches(t, value));
+                match op {
+                    Op::Ne | Op::NoMatch => !found,
+                    _ => found,
+                }
+            }
+            Query::Compare(field, op, value) => {
+                let text = match field {
+                    Field::Title => ctx.item.title.as_str(),
+                    Field::Link => ctx.item.link.as_str(),
+                    Field::Author => ctx.item.author.as_ref().map_or("", |s| s.as_str()),
+                    Field::Summary => ctx.item.summary.as_ref().map_or("", |s| s.as_str()),
+                    Field::Content => ctx.item.content.as_ref().map_or("", |s| s.as_str()),
+                    Field::Feed => ctx.item.feed.as_str(),
+                    Field::Folder => ctx.folder,
+                    Field::Tag | Field::Age => return false,
+                };
+                match op {
+                    Op::Ne | Op::NoMatch => !text_matches(text, value),
+                    _ => text_matches(text, value),
+                }
+            }
+        }
+    }
+}
+
+fn text_matches(text: &str, value: &Value) -> bool {
+    match value {
+        Value::Text(t) => text == t,
+        Value::Pattern(r) => r.is_match(text),
+        Value::Age(_) => false,
+    }
+}
+
+fn compare(age: Duration, op: Op, limit: Duration) -> bool {
+    match op {
+        Op::Lt => age < limit,
+        Op::Le => age <= limit,
+        Op::Gt => age > limit,
+        Op::Ge => age >= limit,
+        Op::Eq => age == limit,
+        _ => age != limit,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Op(Op),
+    Open,
+    Close,
+}
+impl Token {
+    fn text(&self) -> String {
+        match self {
+            Token::Word(w) => w.clone(),
+            Token::Str(s) => format!("\"{}\"", s),
+            Token::Op(o) => format!("{:?}", o),
+            Token::Open => "(".to_string(),
+            Token::Close => ")".to_string(),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            ' ' | '\t' | '\n' => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '"' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(e) => s.push(e),
+                            None => return Err("unterminated string".to_string()),
+                        },
+                        Some(ch) => s.push(ch),
+                        None => return Err("unterminated string".to_string()),
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let next = chars.peek().cloned();
+                let (op, wide) = match (c, next) {
+                    ('=', Some('~')) => (Op::Match, true),
+                    ('=', Some('=')) => (Op::Eq, true),
+                    ('!', Some('~')) => (Op::NoMatch, true),
+                    ('!', Some('=')) => (Op::Ne, true),
+                    ('<', Some('=')) => (Op::Le, true),
+                    ('>', Some('=')) => (Op::Ge, true),
+                    ('=', _) => (Op::Eq, false),
+                    ('<', _) => (Op::Lt, false),
+                    ('>', _) => (Op::Gt, false),
+                    _ => return Err(format!("unknown operator '{}'", c)),
+                };
+                if wide {
+                    chars.next();
+                }
+                tokens.push(Token::Op(op));
+            }
+            _ => {
+                let mut w = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if ch.is_alphanumeric() || ch == '.' || ch == '_' || ch == '-' {
+                        w.push(ch);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                if w.is_empty() {
+                    return Err(format!("unexpected '{}'", c));
+                }
+                tokens.push(Token::Word(w));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let t = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        t
+    }
+
+    fn keyword(&mut self, word: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(w)) if w == word => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn or(&mut self) -> Result<Query, String> {
+        let mut left = self.and()?;
+        while self.keyword("or") {
+            left = Query::Or(Box::new(left), Box::new(self.and()?));
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Query, String> {
+        let mut left = self.not()?;
+        while self.keyword("and") {
+            left = Query::And(Box::new(left), Box::new(self.not()?));
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Query, String> {
+        if self.keyword("not") {
+            return Ok(Query::Not(Box::new(self.not()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Query, String> {
+        let word = match self.next() {
+            Some(Token::Open) => {
+                let q = self.or()?;
+                return match self.next() {
+                    Some(Token::Close) => Ok(q),
+                    _ => Err("missing ')'".to_string()),
+                };
+            }
+            Some(Token::Word(w)) => w,
+            Some(t) => return Err(format!("unexpected '{}'", t.text())),
+            None => return Err("unexpected end of query".to_string()),
+        };
+        let field = match word.as_str() {
+            "unread" => return Ok(Query::Flag(Flag::Unread)),
+            "read" => return Ok(Query::Flag(Flag::Read)),
+            "starred" => return Ok(Query::Flag(Flag::Starred)),
+            "title" => Field::Title,
+            "link" => Field::Link,
+            "author" => Field::Author,
+            "summary" => Field::Summary,
+            "content" => Field::Content,
+            "feed" | "feed.name" => Field::Feed,
+            "folder" | "feed.folder" => Field::Folder,
+            "tag" | "feed.tag" => Field::Tag,
+            "age" => Field::Age,
+            _ => return Err(format!("unknown field '{}'", word)),
+        };
+        let op = match self.next() {
+            Some(Token::Op(op)) => op,
+            _ => return Err(format!("expected an operator after '{}'", word)),
+        };
+        let raw = match self.next() {
+            Some(Token::Str(s)) | Some(Token::Word(s)) => s,
+            _ => return Err(format!("expected a value after '{}'", word)),
+        };
+        let value = match (field, op) {
+            (Field::Age, Op::Match) | (Field::Age, Op::NoMatch) => {
+                return Err("age can only be compared".to_string())
+            }
+            (Field::Age, _) => Value::Age(parse_duration(&raw)?),
+            (_, Op::Match) | (_, Op::NoMatch) => {
+                Value::Pattern(Regex::new(&raw).map_err(|e| e.to_string())?)
+            }
+            (_, Op::Eq) | (_, Op::Ne) => Value::Text(raw),
+            _ => return Err(format!("'{}' can only be compared with = or =~", word)),
+        };
+        Ok(Query::Compare(field, op, value))
+    }
+}
+
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let n: i64 = s[..split]
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", s))?;
+    // the try_ constructors return None where the others would panic
+    let duration = match &s[split..] {
+        "s" => Duration::try_seconds(n),
+        "m" => Duration::try_minutes(n),
+        "h" => Duration::try_hours(n),
+        "d" | "" => Duration::try_days(n),
+        "w" => Duration::try_weeks(n),
+        _ => None,
+    };
+    duration.ok_or_else(|| format!("invalid duration '{}'", s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(seen: bool, starred: bool) -> Item {
+        Item {
+            title: "Rust 2.0 released".to_string(),
+            seen,
+            starred,
+            published_at: Some(Utc::now() - Duration::days(1)),
+            ..Default::default()
+        }
+    }
+
+    fn matches(query: &str, item: &Item) -> bool {
+        let ctx = Context {
+            item,
+            tags: vec!["news"],
+            folder: "tech/lang",
+        };
+        Query::parse(query).unwrap().matches(&ctx)
+    }
+
+    fn error(query: &str) -> String {
+        Query::parse(query).unwrap_err()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        // starred or (unread and read)
+        assert!(matches("starred or unread and read", &item(false, true)));
+        assert!(!matches("starred or unread and read", &item(false, false)));
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and() {
+        // (not starred) and unread
+        assert!(!matches("not starred and unread", &item(true, true)));
+        assert!(matches("not (starred and unread)", &item(true, true)));
+    }
+
+    #[test]
+    fn parentheses_group() {
+        assert!(!matches("(starred or unread) and read", &item(false, true)));
+        assert!(matches("(starred or unread) and read", &item(true, true)));
+    }
+
+    #[test]
+    fn fields() {
+        let i = item(false, false);
+        // backslashes in strings escape the next character
+        assert!(matches(r#"title =~ "^Rust \\d""#, &i));
+        assert!(matches(r#"title != "Rust""#, &i));
+        assert!(matches(r#"tag = news and folder =~ "^tech/""#, &i));
+        assert!(matches("tag != sport", &i));
+        assert!(matches("age < 2d and age > 12h", &i));
+        assert!(!matches("age >= 1w", &i));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error(r#"title = "open"#), "unterminated string");
+        assert_eq!(error("(unread or read"), "missing ')'");
+        assert_eq!(error("unread and"), "unexpected end of query");
+        assert_eq!(error("unread read"), "unexpected 'read'");
+        assert_eq!(error("colour = red"), "unknown field 'colour'");
+        assert_eq!(error("title"), "expected an operator after 'title'");
+        assert_eq!(error("age =~ 1d"), "age can only be compared");
+        assert_eq!(
+            error("title < x"),
+            "'title' can only be compared with = or =~"
+        );
+        assert!(error(r#"title =~ "(""#).contains("regex"));
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("90s"), Ok(Duration::seconds(90)));
+        assert_eq!(parse_duration("3"), Ok(Duration::days(3)));
+        assert_eq!(parse_duration("2w"), Ok(Duration::weeks(2)));
+        assert!(parse_duration("3y").is_err());
+        assert!(parse_duration("d").is_err());
+        assert_eq!(
+            parse_duration("99999999999999d"),
+            Err("invalid duration '99999999999999d'".to_string())
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use quick_xml::escape::escape;
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+use std::collections::HashMap;
+use url::Url;
+
+// the body of an article page, found by scoring the elements that hold its
+// paragraphs, after the approach of arc90's readability
+pub fn extract(page: &str, base: &str) -> Option<String> {
+    lazy_static! {
+        static ref PARAGRAPHS: Selector = Selector::parse("p, pre, td, blockquote").unwrap();
+    }
+    let html = Html::parse_document(page);
+    let mut scores = HashMap::new();
+    for p in html.select(&PARAGRAPHS) {
+        if ancestors(p).any(|a| unlikely(a)) {
+            continue;
+        }
+        let text = text_of(p);
+        if text.chars().count() < 25 {
+            continue;
+        }
+        // longer paragraphs with more clauses count for more
+        let score = 1.0 + text.matches(',').count() as f64 + (text.len() as f64 / 100.0).min(3.0);
+        for (share, parent) in [1.0, 0.5].iter().zip(ancestors(p)) {
+            *scores
+                .entry(parent.id())
+                .or_insert_with(|| initial_score(parent)) += score * share;
+        }
+    }
+
+    let (best, best_score) = scores
+        .iter()
+        .filter_map(|(id, s)| {
+            let el = ElementRef::wrap(html.tree.get(*id)?)?;
+            Some((el, s * (1.0 - link_density(el))))
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+
+    // siblings that scored well, or read like prose, belong to the article
+    let threshold = (best_score * 0.2).max(10.0);
+    let parts: Vec<ElementRef> = match best.parent().and_then(ElementRef::wrap) {
+        Some(parent) => parent
+            .children()
+            .filter_map(ElementRef::wrap)
+            .filter(|s| {
+                s.id() == best.id()
+                    || scores.get(&s.id()).is_some_and(|sc| *sc >= threshold)
+                    || (s.value().name() == "p"
+                        && text_of(*s).len() > 80
+                        && link_density(*s) < 0.25)
+            })
+            .collect(),
+        None => vec![best],
+    };
+
+    let base = Url::parse(base).ok();
+    let mut out = String::new();
+    for part in parts {
+        write_clean(part, base.as_ref(), &mut out);
+    }
+    match text_len(&out) {
+        0 => None,
+        _ => Some(out.trim().to_string()),
+    }
+}
+
+fn ancestors(el: ElementRef) -> impl Iterator<Item = ElementRef> {
+    el.ancestors().filter_map(ElementRef::wrap)
+}
+
+// class names and ids that mark page furniture rather than the article
+fn unlikely(el: ElementRef) -> bool {
+    lazy_static! {
+        static ref UNLIKELY: Regex = Regex::new(
+            r"(?i)comment|footer|sidebar|nav|menu|share|social|related|sponsor|promo|banner|cookie|popup|subscribe|newsletter|disqus|masthead"
+        )
+        .unwrap();
+        static ref MAYBE: Regex = Regex::new(r"(?i)article|body|content|main|post|entry|story").unwrap();
+    }
+    let names = class_and_id(el);
+    UNLIKELY.is_match(&names) && !MAYBE.is_match(&names)
+}
+
+fn initial_score(el: ElementRef) -> f64 {
+    lazy_static! {
+        static ref POSITIVE: Regex = Regex::new(
+            r"(?i)article|body|content|entry|main|page|post|text|blog|story"
+        )
+        .unwrap();
+        static ref NEGATIVE: Regex = Regex::new(
+            r"(?i)comment|meta|footer|footnote|sidebar|widget|share|related|ad-|advert|promo|hidden|byline|author"
+        )
+        .unwrap();
+    }
+    let mut score = match el.value().name() {
+        "article" => 10.0,
+        "div" | "section" | "main" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    let names = class_and_id(el);
+    if POSITIVE.is_match(&names) {
+        score += 25.0;
+    }
+    if NEGATIVE.is_match(&names) {
+        score -= 25.0;
+    }
+    score
+}
+
+fn class_and_id(el: ElementRef) -> String {
+    format!(
+        "{} {}",
+        el.value().attr("class").unwrap_or(""),
+        el.value().attr("id").unwrap_or("")
+    )
+}
+
+// how much of the element's text is inside links
+fn link_density(el: ElementRef) -> f64 {
+    lazy_static! {
+        static ref LINKS: Selector = Selector::parse("a").unwrap();
+    }
+    let total = text_of(el).len();
+    if total == 0 {
+        return 1.0;
+    }
+    let linked: usize = el.select(&LINKS).map(|a| text_of(a).len()).sum();
+    linked as f64 / total as f64
+}
+
+fn text_of(el: ElementRef) -> String {
+    el.text().collect::<String>().trim().to_string()
+}
+
+fn text_len(html: &str) -> usize {
+    Html::parse_fragment(html)
+        .root_element()
+        .text()
+        .map(|t| t.trim().len())
+        .sum()
+}
+
+// tags kept as they are; anything else is replaced by its contents
+const KEEP: &str = "p br hr h1 h2 h3 h4 h5 h6 a img ul ol li dl dt dd blockquote pre code em \
+    strong i b u sub sup figure figcaption table thead tbody tr th td";
+// tags dropped with everything inside them
+const DROP: &str = "script style noscript form button input select textarea iframe object \
+    embed nav aside footer header svg";
+
+fn listed(list: &str, name: &str) -> bool {
+    list.split_whitespace().any(|t| t == name)
+}
+
+fn write_clean(el: ElementRef, base: Option<&Url>, out: &mut String) {
+    let name = el.value().name();
+    if listed(DROP, name) || unlikely(el) {
+        return;
+    }
+    let keep = listed(KEEP, name);
+    if keep {
+        out.push('<');
+        out.push_str(name);
+        for attr in ["href", "src", "alt", "title"].iter() {
+            if let Some(v) = el.value().attr(attr) {
+                let v = match (*attr, base) {
+                    ("href", Some(b)) | ("src", Some(b)) => {
+                        b.join(v).map(|u| u.to_string()).unwrap_or(v.to_string())
+                    }
+                    _ => v.to_string(),
+                };
+                out.push_str(&format!(" {}=\"{}\"", attr, escaped(&v)));
+            }
+        }
+        out.push('>');
+    }
+    for child in el.children() {
+        match child.value() {
+            Node::Text(t) => out.push_str(&escaped(t)),
+            Node::Element(_) => write_clean(ElementRef::wrap(child).unwrap(), base, out),
+            _ => (),
+        }
+    }
+    if keep && !["br", "hr", "img"].contains(&name) {
+        out.push_str(&format!("</{}>", name));
+    }
+}
+
+fn escaped(s: &str) -> String {
+    String::from_utf8_lossy(&escape(s.as_bytes())).into_owned()
+}
@@ -0,0 +1,109 @@
+use regex::bytes::{Captures, Regex};
+
+// the latin-1 entities of HTML, in code point order from U+00A0
+const LATIN1: &str = "nbsp iexcl cent pound curren yen brvbar sect uml copy ordf laquo not shy \
+    reg macr deg plusmn sup2 sup3 acute micro para middot cedil sup1 ordm raquo frac14 frac12 \
+    frac34 iquest Agrave Aacute Acirc Atilde Auml Aring AElig Ccedil Egrave Eacute Ecirc Euml \
+    Igrave Iacute Icirc Iuml ETH Ntilde Ograve Oacute Ocirc Otilde Ouml times Oslash Ugrave \
+    Uacute Ucirc Uuml Yacute THORN szlig agrave aacute acirc atilde auml aring aelig ccedil \
+    egrave eacute ecirc euml igrave iacute icirc iuml eth ntilde ograve oacute ocirc otilde \
+    ouml divide oslash ugrave uacute ucirc uuml yacute thorn yuml";
+
+// and the others that turn up in feeds
+const OTHERS: &str = "OElig:338 oelig:339 Scaron:352 scaron:353 Yuml:376 fnof:402 circ:710 \
+    tilde:732 ensp:8194 emsp:8195 thinsp:8201 zwnj:8204 zwj:8205 lrm:8206 rlm:8207 \
+    ndash:8211 mdash:8212 lsquo:8216 rsquo:8217 sbquo:8218 ldquo:8220 rdquo:8221 bdquo:8222 \
+    dagger:8224 Dagger:8225 bull:8226 hellip:8230 permil:8240 prime:8242 Prime:8243 \
+    lsaquo:8249 rsaquo:8250 euro:8364 trade:8482 larr:8592 rarr:8594 harr:8596";
+
+// fixes the usual ways feeds break XML: bare ampersands and entities that
+// only exist in HTML; CDATA sections are left alone
+pub fn repair(data: &[u8]) -> Vec<u8> {
+    lazy_static! {
+        static ref CDATA: Regex = Regex::new(r"(?s)<!\[CDATA\[.*?\]\]>").unwrap();
+    }
+    let mut out = Vec::with_capacity(data.len());
+    let mut last = 0;
+    for m in CDATA.find_iter(data) {
+        out.extend(escape_ampersands(&data[last..m.start()]));
+        out.extend_from_slice(m.as_bytes());
+        last = m.end();
+    }
+    out.extend(escape_ampersands(&data[last..]));
+    out
+}
+
+fn escape_ampersands(data: &[u8]) -> Vec<u8> {
+    lazy_static! {
+        static ref AMP: Regex =
+            Regex::new(r"&(#[0-9]+;|#[xX][0-9a-fA-F]+;|([A-Za-z][A-Za-z0-9]*);)?").unwrap();
+    }
+    AMP.replace_all(data, |c: &Captures| {
+        let name = match (c.get(1), c.get(2)) {
+            (None, _) => return b"&amp;".to_vec(),
+            (Some(whole), None) => return [b"&", whole.as_bytes()].concat(),
+            (_, Some(name)) => String::from_utf8_lossy(name.as_bytes()).into_owned(),
+        };
+        match name.as_str() {
+            "amp" | "lt" | "gt" | "quot" | "apos" => format!("&{};", name),
+            _ => match html_entity(&name) {
+                Some(code) => format!("&#{};", code),
+                None => format!("&amp;{};", name),
+            },
+        }
+        .into_bytes()
+    })
+    .into_owned()
+}
+
+fn html_entity(name: &str) -> Option<u32> {
+    if let Some(i) = LATIN1.split_whitespace().position(|n| n == name) {
+        return Some(0xa0 + i as u32);
+    }
+    OTHERS
+        .split_whitespace()
+        .filter_map(|e| e.split_once(':'))
+        .find(|(n, _)| *n == name)
+        .and_then(|(_, code)| code.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fix(xml: &str) -> String {
+        String::from_utf8(repair(xml.as_bytes())).unwrap()
+    }
+
+    #[test]
+    fn bare_ampersands() {
+        assert_eq!(
+            fix("<title>Tom & Jerry</title>"),
+            "<title>Tom &amp; Jerry</title>"
+        );
+        assert_eq!(fix("<a>a&b&</a>"), "<a>a&amp;b&amp;</a>");
+        // unknown names are text rather than entities
+        assert_eq!(fix("<a>AT&T;</a>"), "<a>AT&amp;T;</a>");
+    }
+
+    #[test]
+    fn html_entities() {
+        assert_eq!(fix("<a>x&nbsp;y</a>"), "<a>x&#160;y</a>");
+        assert_eq!(fix("caf&eacute; &yuml;"), "caf&#233; &#255;");
+        assert_eq!(fix("a &mdash; b&hellip;"), "a &#8212; b&#8230;");
+    }
+
+    #[test]
+    fn valid_xml_unchanged() {
+        let xml = "<a href=\"?a=1&amp;b=2\">&lt;&gt;&quot;&apos; &#160; &#x2014;</a>";
+        assert_eq!(fix(xml), xml);
+    }
+
+    #[test]
+    fn cdata_untouched() {
+        assert_eq!(
+            fix("<d><![CDATA[Tom & Jerry&nbsp;]]> & <![CDATA[\n&amp;]]></d>"),
+            "<d><![CDATA[Tom & Jerry&nbsp;]]> &amp; <![CDATA[\n&amp;]]></d>"
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use regex::Regex;
+
+use types::{FeedConfig, Item};
+use CONFIG;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleField {
+    Title,
+    Author,
+    Category,
+    Content,
+    Feed,
+    Any,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    Read,
+    Hide,
+    Drop,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Rule {
+    pub field: RuleField,
+    pub pattern: String,
+    pub action: Action,
+}
+
+pub struct Rules {
+    rules: Vec<(Regex, Rule)>,
+}
+impl Rules {
+    // the global rules followed by the feed's own
+    pub fn for_feed(conf: &FeedConfig) -> Self {
+        let rules = CONFIG
+            .rules
+            .iter()
+            .chain(conf.rules.iter())
+            .filter_map(|r| match Regex::new(&r.pattern) {
+                Ok(re) => Some((re, r.clone())),
+                Err(e) => {
+                    warn!("ignoring invalid rule pattern '{}': {}", r.pattern, e);
+                    None
+                }
+            })
+            .collect();
+        Rules { rules }
+    }
+
+    pub fn matching(&self, item: &Item) -> Vec<&Rule> {
+        self.rules
+            .iter()
+            .filter(|(re, rule)| fields(item, rule.field).iter().any(|f| re.is_match(f)))
+            .map(|(_, rule)| rule)
+            .collect()
+    }
+
+    // when several rules match, the strongest action wins
+    pub fn apply(&self, mut item: Item) -> Option<Item> {
+        let action = self.matching(&item).iter().map(|r| r.action).max();
+        match action {
+            Some(Action::Drop) => return None,
+            Some(Action::Hide) => item.hidden = true,
+            Some(Action::Read) => item.seen = true,
+            None => (),
+        }
+        Some(item)
+    }
+}
+
+pub fn fields(item: &Item, field: RuleField) -> Vec<&str> {
+    let mut out = vec![];
+    if field == RuleField::Feed {
+        out.push(item.feed.as_str());
+    }
+    if field == RuleField::Title || field == RuleField::Any {
+        out.push(item.title.as_str());
+    }
+    if field == RuleField::Author || field == RuleField::Any {
+        out.extend(item.author.as_deref());
+    }
+    if field == RuleField::Category || field == RuleField::Any {
+        out.extend(item.categories.iter().map(|s| s.as_str()));
+    }
+    if field == RuleField::Content || field == RuleField::Any {
+        out.extend(item.summary.as_deref());
+        out.extend(item.content.as_deref());
+    }
+    out
+}
@@ -0,0 +1,95 @@
+use ammonia::Builder;
+use regex::{Captures, Regex};
+use url::Url;
+
+// dropped together with everything inside them; other tags that aren't on
+// ammonia's list of safe ones are replaced by their contents
+const DROP: &str = "script style iframe object embed applet noscript template frameset";
+
+// images from these only count the people reading
+const TRACKERS: &str = "doubleclick.net google-analytics.com googletagmanager.com \
+    feeds.feedburner.com feedproxy.google.com feedsportal.com pheedo.com pixel.wp.com \
+    stats.wp.com stats.wordpress.com scorecardresearch.com quantserve.com mathtag.com \
+    buysellads.com list-manage.com mixpanel.com segment.io analytics.twitter.com";
+
+// the html of an item with nothing left that runs, loads from elsewhere
+// behind the reader's back or tracks them; plain text is treated as html
+// too, so that a tag cut off at the end can't come alive once it's embedded
+pub fn sanitize(html: &str) -> String {
+    lazy_static! {
+        static ref SANITIZER: Builder<'static> = {
+            let mut b = Builder::default();
+            b.add_clean_content_tags(DROP.split_whitespace());
+            b
+        };
+        // ammonia writes every attribute value in double quotes
+        static ref IMG: Regex = Regex::new(r#"<img\b(?:[^>"]|"[^"]*")*>"#).unwrap();
+    }
+    let clean = SANITIZER.clean(html).to_string();
+    IMG.replace_all(&clean, |c: &Captures| match tracking(&c[0]) {
+        true => String::new(),
+        false => c[0].to_string(),
+    })
+    .into_owned()
+}
+
+// a tracking pixel is an image of at most one pixel, or one from a tracker
+fn tracking(img: &str) -> bool {
+    lazy_static! {
+        static ref TINY: Regex = Regex::new(r#"\b(?:width|height)="\s*[01](?:px)?\s*""#).unwrap();
+        static ref SRC: Regex = Regex::new(r#"\bsrc="([^"]*)""#).unwrap();
+    }
+    if TINY.is_match(img) {
+        return true;
+    }
+    let host = SRC
+        .captures(img)
+        .and_then(|c| Url::parse(&c[1].replace("&amp;", "&")).ok())
+        .and_then(|u| u.host_str().map(|h| h.to_lowercase()));
+    match host {
+        Some(h) => TRACKERS
+            .split_whitespace()
+            .any(|t| h == t || h.ends_with(&format!(".{}", t))),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_what_runs() {
+        assert_eq!(
+            sanitize("<p onclick=\"x()\">hi<script>alert(1)</script></p>"),
+            "<p>hi</p>"
+        );
+        assert_eq!(
+            sanitize("<a href=\"javascript:alert(1)\">x</a><style>p{}</style>"),
+            "<a rel=\"noopener noreferrer\">x</a>"
+        );
+        assert_eq!(sanitize("<iframe src=\"https://x.com/\">y</iframe>z"), "z");
+    }
+
+    #[test]
+    fn plain_text_is_escaped() {
+        assert_eq!(sanitize("Tom & Jerry <3"), "Tom &amp; Jerry &lt;3");
+        // a tag cut off at the end of the text
+        assert_eq!(sanitize("teaser <img src=x onerror=alert(1)"), "teaser ");
+        assert_eq!(sanitize("a <b"), "a ");
+    }
+
+    #[test]
+    fn tracking_pixels() {
+        assert_eq!(
+            sanitize("<img src=\"https://example.com/a.png\" width=\"1\" height=\"1\">"),
+            ""
+        );
+        assert_eq!(
+            sanitize("<img src=\"https://stats.wp.com/b.gif?a=1&amp;b=2\">"),
+            ""
+        );
+        let img = "<img src=\"https://example.com/a.png\" width=\"100\">";
+        assert_eq!(sanitize(img), img);
+    }
+}
@@ -0,0 +1,56 @@
+use regex::Regex;
+
+use rules::{fields, RuleField};
+use types::Item;
+
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct Scoring {
+    #[serde(default)]
+    pub highlight: Option<i64>,
+    #[serde(default)]
+    pub rules: Vec<ScoreRule>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ScoreRule {
+    pub field: RuleField,
+    pub pattern: String,
+    pub score: i64,
+}
+
+pub struct Scorer {
+    rules: Vec<(Regex, i64, RuleField)>,
+    highlight: Option<i64>,
+}
+impl Scorer {
+    pub fn new(conf: &Scoring) -> Self {
+        let rules = conf
+            .rules
+            .iter()
+            .filter_map(|r| match Regex::new(&r.pattern) {
+                Ok(re) => Some((re, r.score, r.field)),
+                Err(e) => {
+                    warn!("ignoring invalid score pattern '{}': {}", r.pattern, e);
+                    None
+                }
+            })
+            .collect();
+        Scorer {
+            rules,
+            highlight: conf.highlight,
+        }
+    }
+
+    // every matching rule adds its score
+    pub fn score(&self, item: &Item) -> i64 {
+        self.rules
+            .iter()
+            .filter(|(re, _, field)| fields(item, *field).iter().any(|f| re.is_match(f)))
+            .map(|(_, score, _)| score)
+            .sum()
+    }
+
+    pub fn is_highlighted(&self, score: i64) -> bool {
+        self.highlight.is_some_and(|h| score >= h)
+    }
+}
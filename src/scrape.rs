@@ -0,0 +1,104 @@
+use scraper::{ElementRef, Html, Selector};
+use url::Url;
+
+use dates::parse_date_with;
+use types::{Feed, Item};
+
+// turns a page without a feed into items, one per element matching `item`;
+// the other selectors are relative to that element
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Scrape {
+    pub item: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub date_formats: Vec<String>,
+}
+
+pub fn parse(data: &[u8], url: &str, conf: &Scrape) -> Result<(Feed, Vec<Item>), String> {
+    let html = Html::parse_document(&String::from_utf8_lossy(data));
+    let base = Url::parse(url).ok();
+    let item_sel = selector(&conf.item)?;
+    let title_sel = conf.title.as_deref().map(selector).transpose()?;
+    let link_sel = selector(conf.link.as_deref().unwrap_or("a[href]"))?;
+    let date_sel = conf.date.as_deref().map(selector).transpose()?;
+    let summary_sel = conf.summary.as_deref().map(selector).transpose()?;
+
+    let feed = Feed {
+        title: html
+            .select(&selector("title")?)
+            .next()
+            .map(text)
+            .unwrap_or_default(),
+        site_link: Some(url.to_string()),
+        ..Default::default()
+    };
+
+    let mut items = vec![];
+    for el in html.select(&item_sel) {
+        let title = match title_sel {
+            Some(ref s) => el.select(s).next().map(text).unwrap_or_default(),
+            None => text(el),
+        };
+        // the container itself may be the link
+        let href = match el.value().attr("href") {
+            Some(h) if conf.link.is_none() => Some(h),
+            _ => el
+                .select(&link_sel)
+                .next()
+                .and_then(|a| a.value().attr("href")),
+        };
+        let link = href
+            .map(|h| match base.as_ref().and_then(|b| b.join(h).ok()) {
+                Some(u) => u.to_string(),
+                None => h.to_string(),
+            })
+            .unwrap_or_default();
+        // <time datetime=".."> is more reliable than its text
+        let published_at = date_sel
+            .as_ref()
+            .and_then(|s| el.select(s).next())
+            .and_then(|d| {
+                let value = d.value().attr("datetime").map(|s| s.to_string());
+                parse_date_with(&value.unwrap_or_else(|| text(d)), &conf.date_formats)
+            });
+        let summary = summary_sel
+            .as_ref()
+            .and_then(|s| el.select(s).next())
+            .map(text);
+        if title.is_empty() && link.is_empty() {
+            continue;
+        }
+        items.push(Item {
+            guid: if link.is_empty() {
+                title.clone()
+            } else {
+                link.clone()
+            },
+            title,
+            link,
+            summary,
+            published_at,
+            ..Default::default()
+        });
+    }
+    Ok((feed, items))
+}
+
+fn selector(s: &str) -> Result<Selector, String> {
+    Selector::parse(s).map_err(|e| format!("invalid selector '{}': {}", s, e))
+}
+
+// the element's text with runs of whitespace collapsed
+fn text(el: ElementRef) -> String {
+    el.text()
+        .flat_map(|t| t.split_whitespace())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,137 @@
+use serde::Serialize;
+use serde_json;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use podcast::Download;
+use types::{Feed, FeedConfig, Item};
+use CONFIG;
+
+pub fn feed_dir(name: &str) -> PathBuf {
+    CONFIG.feed_path.join(name)
+}
+
+pub fn items_dir(name: &str) -> PathBuf {
+    feed_dir(name).join("items")
+}
+
+fn item_path(feed: &str, id: &str) -> PathBuf {
+    items_dir(feed).join(format!("{}.json", id))
+}
+
+// feeds
+
+pub fn load_feed(name: &str) -> Option<Feed> {
+    read_json(&feed_dir(name).join("feed.json"))
+}
+
+pub fn save_feed(feed: &Feed) {
+    write_json(&feed_dir(&feed.name).join("feed.json"), feed);
+}
+
+// tags set in the config plus any added with `mercury tag`
+pub fn feed_tags(conf: &FeedConfig) -> Vec<String> {
+    let mut tags = conf.tags.clone();
+    if let Some(feed) = load_feed(&conf.name()) {
+        tags.extend(feed.tags);
+    }
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+pub fn feed_names() -> Vec<String> {
+    let mut names: Vec<String> = match fs::read_dir(&CONFIG.feed_path) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().join("feed.json").exists())
+            .filter_map(|e| e.file_name().into_string().ok())
+            .collect(),
+        Err(_) => vec![],
+    };
+    names.sort();
+    names
+}
+
+// items
+
+pub fn load_item(feed: &str, id: &str) -> Option<Item> {
+    read_json(&item_path(feed, id))
+}
+
+pub fn find_item(id: &str) -> Option<Item> {
+    feed_names().iter().filter_map(|f| load_item(f, id)).next()
+}
+
+pub fn load_items(feed: &str) -> Vec<Item> {
+    match fs::read_dir(items_dir(feed)) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| read_json::<Item>(&e.path()))
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+pub fn save_item(item: &Item) {
+    write_json(&item_path(&item.feed, &item.id), item);
+}
+
+pub fn delete_item(item: &Item) {
+    if let Err(e) = fs::remove_file(item_path(&item.feed, &item.id)) {
+        error!("could not delete {}: {}", item.id, e);
+    }
+}
+
+pub fn mark_item_as_read(item: &mut Item) {
+    if !item.seen {
+        item.seen = true;
+        save_item(item);
+    }
+}
+
+pub fn set_item_starred(item: &mut Item, starred: bool) {
+    if item.starred != starred {
+        item.starred = starred;
+        save_item(item);
+    }
+}
+
+// podcast downloads
+
+fn queue_path() -> PathBuf {
+    CONFIG.feed_path.join("podcasts.json")
+}
+
+pub fn load_queue() -> Vec<Download> {
+    read_json(&queue_path()).unwrap_or_default()
+}
+
+pub fn save_queue(queue: &[Download]) {
+    write_json(&queue_path(), &queue);
+}
+
+pub fn read_json<T>(path: &Path) -> Option<T>
+where
+    for<'de> T: ::serde::Deserialize<'de>,
+{
+    let data = fs::read(path).ok()?;
+    match serde_json::from_slice(&data) {
+        Ok(v) => Some(v),
+        Err(e) => {
+            error!("could not read {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+// write to a sibling file first so a crash never leaves a truncated record
+pub fn write_json<T: Serialize>(path: &Path, value: &T) {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).expect("could not create store directory");
+    }
+    let tmp = path.with_extension("json.tmp");
+    let data = serde_json::to_vec_pretty(value).expect("could not serialize record");
+    fs::write(&tmp, data).expect("could not write record");
+    fs::rename(&tmp, path).expect("could not write record");
+}
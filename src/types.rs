@@ -1,24 +1,204 @@
+use chrono::{DateTime, Utc};
 use dirs;
+use regex::Regex;
 use std::env;
-use std::fs::{self, File};
+use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
 
+use cleaner::UrlCleaning;
+use folders::split_folder;
+use hooks::Hook;
+use images::ImageCache;
+use notify::Notify;
+use open::Open;
+use podcast::Podcasts;
+use prune::Retention;
+use rules::Rule;
+use score::Scoring;
+use scrape::Scrape;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct Feed {
-    filename: String,
+    pub name: String,
+    pub url: String,
+    pub title: String,
+    pub site_link: Option<String>,
+    pub description: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+    #[serde(default)]
+    pub warning: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // ids of items pruned while the feed still had them
+    #[serde(default)]
+    pub pruned: Vec<String>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct Item {
-    filename: String,
+    pub id: String,
+    pub feed: String,
+    pub guid: String,
+    pub title: String,
+    pub link: String,
+    pub author: Option<String>,
+    pub summary: Option<String>,
+    pub content: Option<String>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub seen: bool,
+    #[serde(default)]
+    pub starred: bool,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    #[serde(default)]
+    pub enclosures: Vec<Enclosure>,
+    #[serde(default)]
+    pub thumbnail: Option<String>,
+    #[serde(default)]
+    pub episode: Option<Episode>,
+}
+
+// a media file attached to an item, as with podcasts
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Enclosure {
+    pub url: String,
+    pub mime_type: Option<String>,
+    pub length: Option<u64>,
+    pub duration: Option<String>,
+}
+
+// podcast metadata from the itunes and podcasting 2.0 namespaces
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Episode {
+    pub duration: Option<String>,
+    pub episode: Option<u32>,
+    pub season: Option<u32>,
+    pub image: Option<String>,
+    pub chapters: Option<String>,
+    #[serde(default)]
+    pub transcripts: Vec<Transcript>,
+    // the text of a downloaded transcript
+    pub transcript: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Transcript {
+    pub url: String,
+    pub mime_type: Option<String>,
+    pub language: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct FeedConfig {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub folder: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<Rule>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scrape: Option<Scrape>,
+    // fetch each new item's page for the whole article
+    #[serde(default, skip_serializing_if = "::std::ops::Not::not")]
+    pub full_text: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention: Option<Retention>,
+}
+impl FeedConfig {
+    pub fn name(&self) -> String {
+        match self.name {
+            Some(ref n) => n.clone(),
+            None => Self::slug(&self.url),
+        }
+    }
+
+    // seconds to wait for a request or command
+    pub fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout.unwrap_or(30))
+    }
+
+    pub fn folder_path(&self) -> Vec<String> {
+        match self.folder {
+            Some(ref f) => split_folder(f),
+            None => vec![],
+        }
+    }
+
+    fn slug(url: &str) -> String {
+        lazy_static! {
+            static ref SCHEME: Regex = Regex::new(r"^[a-zA-Z][a-zA-Z0-9+.-]*://").unwrap();
+            static ref UNSAFE: Regex = Regex::new(r"[^a-zA-Z0-9]+").unwrap();
+        }
+        let stripped = SCHEME.replace(url, "");
+        UNSAFE
+            .replace_all(&stripped, "-")
+            .trim_matches('-')
+            .to_lowercase()
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct QueryFeed {
+    pub name: String,
+    pub query: String,
 }
 
 #[derive(Deserialize, Debug)]
 pub struct Config {
-    feed_path: PathBuf,
+    #[serde(default = "Config::default_feed_dir")]
+    pub feed_path: PathBuf,
+    #[serde(default)]
+    pub feeds: Vec<FeedConfig>,
+    #[serde(default)]
+    pub queries: Vec<QueryFeed>,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    #[serde(default)]
+    pub scoring: Scoring,
+    #[serde(default)]
+    pub hooks: Vec<Hook>,
+    #[serde(default)]
+    pub notify: Notify,
+    #[serde(default)]
+    pub podcasts: Podcasts,
+    #[serde(default)]
+    pub open: Open,
+    #[serde(default)]
+    pub images: ImageCache,
+    #[serde(default)]
+    pub retention: Retention,
+    #[serde(default)]
+    pub url_cleaning: UrlCleaning,
 }
 impl Config {
     pub fn defaults() -> Self {
         let c = Config {
             feed_path: Self::default_feed_dir(),
+            feeds: vec![],
+            queries: vec![],
+            rules: vec![],
+            scoring: Scoring::default(),
+            hooks: vec![],
+            notify: Notify::default(),
+            podcasts: Podcasts::default(),
+            open: Open::default(),
+            images: ImageCache::default(),
+            retention: Retention::default(),
+            url_cleaning: UrlCleaning::default(),
         };
         c.check_feed_dir();
         c
@@ -26,7 +206,7 @@ impl Config {
 
     fn check_feed_dir(&self) {
         if !self.feed_path.exists() {
-            fs::create_dir(&self.feed_path);
+            fs::create_dir_all(&self.feed_path).expect("could not create feed directory");
         }
     }
 
@@ -47,4 +227,12 @@ impl Config {
         config.check_feed_dir();
         config
     }
+
+    pub fn find_feed(&self, name: &str) -> Option<&FeedConfig> {
+        self.feeds.iter().find(|f| f.name() == name)
+    }
+
+    pub fn find_query(&self, name: &str) -> Option<&QueryFeed> {
+        self.queries.iter().find(|q| q.name == name)
+    }
 }
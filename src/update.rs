@@ -0,0 +1,224 @@
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::slice;
+use std::thread;
+use std::time::Duration;
+use url::Url;
+
+use encoding::to_utf8;
+use fetch::{fetch, run_command};
+use hooks::{run_batch_hooks, run_item_hooks};
+use images::cache_images;
+use local::{local_path, read_dir};
+use notify::notify_new_items;
+use parser::parse;
+use prune::prune_all;
+use readability::extract;
+use rules::Rules;
+use sanitize::sanitize;
+use scrape;
+use store::{feed_tags, load_feed, load_item, save_feed, save_item};
+use types::{Feed, FeedConfig, Item};
+use {CLEANER, CONFIG};
+
+// sources starting with this run a shell command instead of a request
+const EXEC: &str = "exec:";
+
+pub fn update_all(tag: Option<&str>) {
+    let mut new_items = vec![];
+    for conf in &CONFIG.feeds {
+        if let Some(t) = tag {
+            if !feed_tags(conf).iter().any(|f| f == t) {
+                continue;
+            }
+        }
+        match update_feed(conf) {
+            Ok(ref items) if items.is_empty() => debug!("no new items for {}", conf.name()),
+            Ok(items) => {
+                info!("found {} new items for {}", items.len(), conf.name());
+                run_item_hooks(&items);
+                new_items.extend(items);
+            }
+            Err(e) => error!("could not update {}: {}", conf.name(), e),
+        }
+    }
+    run_batch_hooks(&new_items);
+    notify_new_items(&new_items);
+    prune_all();
+}
+
+// updates forever, sleeping between rounds
+pub fn daemon(interval: u64, tag: Option<&str>) {
+    info!("updating every {} seconds", interval);
+    loop {
+        update_all(tag);
+        thread::sleep(Duration::from_secs(interval));
+    }
+}
+
+pub fn update_feed(conf: &FeedConfig) -> Result<Vec<Item>, String> {
+    let name = conf.name();
+    let mut feed = load_feed(&name).unwrap_or_default();
+    feed.name = name.clone();
+    feed.url = conf.url.clone();
+
+    let (parsed, items) = match fetch_items(conf) {
+        Ok(r) => r,
+        Err(e) => {
+            feed.error = Some(e.clone());
+            save_feed(&feed);
+            return Err(e);
+        }
+    };
+    feed.title = parsed.title;
+    feed.site_link = parsed.site_link;
+    feed.description = parsed.description;
+    feed.updated_at = Some(Utc::now());
+    feed.error = None;
+    if let Some(ref w) = parsed.warning {
+        warn!("{}: {}", name, w);
+    }
+    feed.warning = parsed.warning;
+    // forget pruned items once the feed has dropped them too
+    feed.pruned.retain(|id| items.iter().any(|i| i.id == *id));
+    save_feed(&feed);
+
+    let rules = Rules::for_feed(conf);
+    let mut new_items = items
+        .into_iter()
+        .filter(|item| !feed.pruned.contains(&item.id))
+        .filter_map(|item| rules.apply(item))
+        .filter_map(|item| process_duplicate(conf, item))
+        .collect::<Vec<Item>>();
+    if conf.full_text {
+        new_items.iter_mut().for_each(|i| full_text(conf, i));
+    }
+    if CONFIG.images.enabled {
+        cache_images(&new_items);
+    }
+    new_items.iter().for_each(save_item);
+    Ok(new_items)
+}
+
+// fetches and parses a feed without touching the store
+pub fn fetch_items(conf: &FeedConfig) -> Result<(Feed, Vec<Item>), String> {
+    let name = conf.name();
+    let (feed, mut items) = match local_path(&conf.url).filter(|p| p.is_dir()) {
+        Some(dir) => read_dir(&dir)?,
+        None => fetch_document(conf)?,
+    };
+    for item in &mut items {
+        item.feed = name.clone();
+        item.id = item_id(&name, &item.guid);
+        item.link = CLEANER.clean(&item.link);
+        item.content = item.content.as_ref().map(|c| sanitize(c));
+        item.summary = item.summary.as_ref().map(|s| sanitize(s));
+    }
+    Ok((feed, items))
+}
+
+// only a feed's own url from the config may name a command or a file
+fn fetch_document(conf: &FeedConfig) -> Result<(Feed, Vec<Item>), String> {
+    let (data, content_type) = match (conf.url.strip_prefix(EXEC), local_path(&conf.url)) {
+        (Some(command), _) => (
+            run_command(command.trim(), None, &[], conf.timeout())?,
+            None,
+        ),
+        (None, Some(path)) => (
+            fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?,
+            None,
+        ),
+        (None, None) => fetch(&conf.url, conf.timeout())?,
+    };
+    // the filter gets the document as fetched and prints the one to parse,
+    // so only what it prints is decoded
+    let data = match conf.filter {
+        Some(ref filter) => to_utf8(run_command(filter, Some(&data), &[], conf.timeout())?, None),
+        None => to_utf8(data, content_type.as_deref()),
+    };
+    match conf.scrape {
+        Some(ref scrape) => scrape::parse(&data, &conf.url, scrape),
+        None => parse(&data),
+    }
+}
+
+// replaces a teaser with the article from the item's page, keeping the
+// teaser when the page can't be fetched or has less to say
+fn full_text(conf: &FeedConfig, item: &mut Item) {
+    if item.link.is_empty() {
+        return;
+    }
+    // relative links are relative to the feed, not to anything local
+    let link = match Url::parse(&conf.url).and_then(|base| base.join(&item.link)) {
+        Ok(l) => l.to_string(),
+        Err(_) => item.link.clone(),
+    };
+    let page = match fetch(&link, conf.timeout()) {
+        Ok((body, content_type)) => to_utf8(body, content_type.as_deref()),
+        Err(e) => {
+            warn!("could not fetch the article for {}: {}", item.id, e);
+            return;
+        }
+    };
+    let known = item.content.as_ref().map_or(0, |c| c.len());
+    match extract(&String::from_utf8_lossy(&page), &link) {
+        Some(article) if article.len() > known => item.content = Some(sanitize(&article)),
+        Some(_) => debug!("feed content of {} is longer than its page", item.id),
+        None => warn!("found no article on {}", link),
+    }
+}
+
+// an item we already have is only rewritten if the publisher changed it,
+// keeping the local state intact
+fn process_duplicate(conf: &FeedConfig, mut item: Item) -> Option<Item> {
+    match load_item(&item.feed, &item.id) {
+        Some(existing) => {
+            // undated items keep the time we first saw them
+            if item.published_at.is_none() {
+                item.published_at = existing.published_at;
+            }
+            if existing.published_at != item.published_at || existing.updated_at != item.updated_at
+            {
+                debug!("found updated item {}", item.id);
+                // a downloaded transcript is ours, not the publisher's
+                if let Some(ep) = item.episode.as_mut() {
+                    ep.transcript = existing.episode.and_then(|e| e.transcript);
+                }
+                // the article fetched before beats the feed's teaser when
+                // the page can't be fetched again
+                if conf.full_text {
+                    let teaser = item.content.clone();
+                    full_text(conf, &mut item);
+                    let len = |c: &Option<String>| c.as_ref().map_or(0, |c| c.len());
+                    if item.content == teaser && len(&existing.content) > len(&teaser) {
+                        item.content = existing.content;
+                    }
+                }
+                if CONFIG.images.enabled {
+                    cache_images(slice::from_ref(&item));
+                }
+                save_item(&Item {
+                    seen: existing.seen,
+                    starred: existing.starred,
+                    tags: existing.tags,
+                    hidden: existing.hidden || item.hidden,
+                    ..item
+                });
+            }
+            None
+        }
+        None => {
+            item.published_at.get_or_insert_with(Utc::now);
+            Some(item)
+        }
+    }
+}
+
+pub fn item_id(feed: &str, guid: &str) -> String {
+    let mut hasher = Sha256::default();
+    hasher.input(feed.as_bytes());
+    hasher.input(guid.as_bytes());
+    let output = hasher.result();
+    output[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}